@@ -0,0 +1,519 @@
+//! [`AsyncFetcher`][AsyncFetcher] is the `async` counterpart to [`Fetcher`][crate::fetcher::Fetcher].
+//!
+//! This is only available with the `async` feature. Requests are done with `reqwest` and the delays
+//! between keyless requests are coordinated with `tokio`, so fetching never blocks the runtime.
+//! Just like the blocking API, fetchers from the same [`AsyncSession`][AsyncSession] coordinate
+//! their delays with each other. Note that an `AsyncSession` and a blocking
+//! [`Session`][crate::session::Session] don't know about each other, so stick to one or the other.
+
+use std::{future::Future, sync::Arc, time::Instant};
+
+use crate::{
+    constants,
+    errors::ApiError,
+    fetcher::{Fetcher, Step},
+    opts::Opts,
+    policy::FetchPolicy,
+    proxy::Proxy,
+    session::Session,
+};
+
+use futures_util::stream::{self, StreamExt};
+use tokio::{sync::Mutex, time};
+
+lazy_static! {
+    static ref GLOBAL: AsyncSession = AsyncSession::new();
+}
+
+/// State shared between [`AsyncFetcher`][AsyncFetcher]s.
+///
+/// This is the `async` version of [`Session`][crate::session::Session]. `AsyncFetcher::new` uses a
+/// global `AsyncSession` so this is only needed when you want to configure the shared state.
+///
+/// ```
+/// use lead_oxide::{async_fetcher::AsyncSession, opts::Opts};
+///
+/// let session = AsyncSession::new().with_daily_limit(50);
+/// let mut fetcher = session.fetcher(Opts::default());
+/// ```
+#[derive(Clone, Debug)]
+pub struct AsyncSession {
+    // Keeps track of the requests, daily limit, policy, and uri the same way as the blocking API
+    session: Session,
+    last_fetched: Arc<Mutex<Option<Instant>>>,
+    client: reqwest::Client,
+}
+
+impl AsyncSession {
+    /// Creates a new `AsyncSession` with no daily limit. The first fetch from a new `AsyncSession`
+    /// won't be delayed.
+    pub fn new() -> Self {
+        Self {
+            session: Session::new(),
+            last_fetched: Arc::new(Mutex::new(None)),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Uses `client` for all requests to the API instead of building one internally.
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Sets the maximum number of requests that can be made within a rolling 24 hour window. This
+    /// works the same as [`Session::with_daily_limit`][crate::session::Session::with_daily_limit].
+    pub fn with_daily_limit(mut self, daily_limit: usize) -> Self {
+        self.session = self.session.with_daily_limit(daily_limit);
+        self
+    }
+
+    /// Sets the [`FetchPolicy`][crate::policy::FetchPolicy] used by every
+    /// [`AsyncFetcher`][AsyncFetcher] created from this `AsyncSession`.
+    pub fn with_policy(mut self, policy: FetchPolicy) -> Self {
+        self.session = self.session.with_policy(policy);
+        self
+    }
+
+    /// Sends requests to `uri` instead of pubproxy.com. Each [`AsyncFetcher`][AsyncFetcher] keeps
+    /// its own copy when it's created.
+    pub fn with_uri(mut self, uri: impl Into<String>) -> Self {
+        self.session = self.session.with_uri(uri);
+        self
+    }
+
+    /// The number of requests made within the last 24 hours.
+    pub fn requests_today(&self) -> usize {
+        self.session.requests_today()
+    }
+
+    /// Creates an [`AsyncFetcher`][AsyncFetcher] that uses this `AsyncSession`.
+    pub fn fetcher(&self, opts: Opts) -> AsyncFetcher {
+        AsyncFetcher::with_session(opts, self.clone())
+    }
+}
+
+impl Default for AsyncSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The `async` entrypoint into the API.
+///
+/// This works the same as a [`Fetcher`][crate::fetcher::Fetcher], keeping an internal list of
+/// proxies so that as few requests as possible are made. Repeated sockets and recently failed
+/// proxies are filtered out of responses the same way too.
+///
+/// ```no_run
+/// use lead_oxide::{async_fetcher::AsyncFetcher, errors::ApiError, opts::Opts};
+///
+/// async fn five_proxies() -> Result<(), ApiError> {
+///     let mut fetcher = AsyncFetcher::new(Opts::default());
+///     let proxies = fetcher.try_get(5).await?;
+///     println!("{:#?}", proxies);
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct AsyncFetcher {
+    // Holds the internal list and does all of the same filtering, just without ever requesting
+    fetcher: Fetcher,
+    session: AsyncSession,
+}
+
+impl AsyncFetcher {
+    /// Creates a new `AsyncFetcher` with a given set of `Opts` that uses the global
+    /// [`AsyncSession`][AsyncSession].
+    pub fn new(opts: Opts) -> Self {
+        Self::with_session(opts, GLOBAL.clone())
+    }
+
+    /// Internal
+    pub(crate) fn with_session(opts: Opts, session: AsyncSession) -> Self {
+        Self {
+            fetcher: session.session.fetcher(opts),
+            session,
+        }
+    }
+
+    /// Attempts to get the specified amount of proxies from the API, returning an error if there
+    /// was an issue with the API. Like [`Fetcher::try_get`][crate::fetcher::Fetcher::try_get], any
+    /// proxies returned before an error was encountered are kept in the internal list, and
+    /// [`ApiError::Exhausted`][ApiError::Exhausted] is returned if a request stops turning up any
+    /// new proxies. Asking for `0` proxies always returns right away without making any requests.
+    pub async fn try_get(&mut self, amount: usize) -> Result<Vec<Proxy>, ApiError> {
+        if amount == 0 {
+            return Ok(Vec::new());
+        }
+
+        while self.fetcher.available() < amount {
+            let proxies = self.fetch_delayed().await?;
+            if self.fetcher.buffer_page(proxies) == 0 {
+                return Err(ApiError::Exhausted);
+            }
+        }
+
+        Ok(self.fetcher.take_without_prefetch(amount))
+    }
+
+    /// Attempts to get `amount` proxies that `validate` approves of, which allows for running your
+    /// own async checks (like making a request to your actual target) as part of fetching.
+    /// Validations are run concurrently with a handful at a time, and rejected proxies are
+    /// [reported as failures][AsyncFetcher::report_failure]. If not enough proxies are approved
+    /// after several requests then [`ApiError::AttemptsExhausted`][ApiError::AttemptsExhausted] is
+    /// returned. Like [`try_get`][AsyncFetcher::try_get], any approved proxies are kept in the
+    /// internal list when an error is returned.
+    ///
+    /// ```no_run
+    /// use lead_oxide::{async_fetcher::AsyncFetcher, opts::Opts, proxy::Proxy};
+    ///
+    /// async fn check(proxy: Proxy) -> bool {
+    ///     // ... make a request through the proxy
+    ///     true
+    /// }
+    ///
+    /// async fn validated() {
+    ///     let mut fetcher = AsyncFetcher::new(Opts::default());
+    ///     let proxies = fetcher.try_get_async_validated(3, check).await.unwrap();
+    /// }
+    /// ```
+    pub async fn try_get_async_validated<F, Fut>(
+        &mut self,
+        amount: usize,
+        validate: F,
+    ) -> Result<Vec<Proxy>, ApiError>
+    where
+        F: Fn(Proxy) -> Fut,
+        Fut: Future<Output = bool>,
+    {
+        let mut approved = Vec::with_capacity(amount);
+        let mut attempts = 0;
+
+        while approved.len() < amount {
+            if self.fetcher.available() == 0 {
+                if attempts == self.fetcher.policy().max_attempts {
+                    self.fetcher.restore(approved);
+                    return Err(ApiError::AttemptsExhausted { attempts });
+                }
+                attempts += 1;
+
+                match self.fetch_delayed().await {
+                    Ok(proxies) => {
+                        self.fetcher.buffer_page(proxies);
+                    }
+                    Err(err) => {
+                        self.fetcher.restore(approved);
+                        return Err(err);
+                    }
+                }
+            }
+
+            let candidates = self.fetcher.take_without_prefetch(amount - approved.len());
+            let checked: Vec<_> = stream::iter(candidates)
+                .map(|proxy| {
+                    let approval = validate(proxy.clone());
+                    async move { (approval.await, proxy) }
+                })
+                .buffered(constants::MAX_CONCURRENT_VALIDATIONS)
+                .collect()
+                .await;
+            for (ok, proxy) in checked {
+                if ok {
+                    approved.push(proxy);
+                } else {
+                    self.fetcher.report_failure(&proxy);
+                }
+            }
+        }
+
+        Ok(approved)
+    }
+
+    /// Reports that `proxy` failed, so any proxy with the same socket is skipped until the failure
+    /// TTL has passed. This works the same as
+    /// [`Fetcher::report_failure`][crate::fetcher::Fetcher::report_failure].
+    pub fn report_failure(&mut self, proxy: &Proxy) {
+        self.fetcher.report_failure(proxy);
+    }
+
+    /// Consumes the `AsyncFetcher` returning any proxies still left in the internal list.
+    pub fn drain(self) -> Vec<Proxy> {
+        self.fetcher.drain()
+    }
+
+    // Does a single request to the API while coordinating delays with the `AsyncSession`
+    async fn fetch_delayed(&self) -> Result<Vec<Proxy>, ApiError> {
+        self.fetcher.check_daily_limit()?;
+
+        if self.fetcher.is_premium() {
+            // Same as the blocking version, there's no delay with an api key
+            self.fetch().await
+        } else {
+            // Holding the lock across the delay and request keeps this to one request per delay
+            // while only suspending the task instead of the thread
+            let mut last_fetched = self.session.last_fetched.lock().await;
+
+            if let Some(last_fetched) = *last_fetched {
+                let delta = Instant::now().duration_since(last_fetched);
+                let delay = self.fetcher.policy().delay;
+                if delta < delay {
+                    let wait = delay - delta;
+                    debug!("Waiting {:?} before the next keyless request", wait);
+                    time::sleep(wait).await;
+                }
+            }
+
+            let proxies = self.fetch().await;
+
+            *last_fetched = Some(Instant::now());
+
+            proxies
+        }
+    }
+
+    async fn fetch(&self) -> Result<Vec<Proxy>, ApiError> {
+        let opts = self.fetcher.opts();
+        let session = self.fetcher.session();
+        let countries = opts.allowed_countries();
+        let mut retries = 0;
+        let mut transient_retries = 0;
+
+        loop {
+            debug!("Requesting {}", self.fetcher.logged_uri(opts));
+            session.record_request();
+            let start = Instant::now();
+
+            // Responses are mocked out when testing
+            #[cfg(not(test))]
+            let naive_resp = self.request().await;
+            #[cfg(test)]
+            let naive_resp = {
+                let _ = opts.to_query_string()?;
+                let nth = session.requests_today() - 1;
+                Ok(crate::mock::response(opts.limit as usize, nth))
+            };
+
+            session.record_latency(&countries, start.elapsed());
+            match self
+                .fetcher
+                .classify(naive_resp, &mut retries, &mut transient_retries)
+            {
+                Step::Retry(wait) => time::sleep(wait).await,
+                Step::Done(result) => {
+                    let proxies = result.map(|(proxies, _)| proxies);
+                    if let Ok(proxies) = &proxies {
+                        debug!(
+                            "Got {} proxies from {}",
+                            proxies.len(),
+                            self.fetcher.logged_uri(opts)
+                        );
+                    }
+                    return proxies;
+                }
+            }
+        }
+    }
+
+    #[cfg(not(test))]
+    async fn request(&self) -> Result<crate::types::NaiveResponse, ApiError> {
+        let opts = self.fetcher.opts();
+        let policy = self.fetcher.policy();
+        // `reqwest` serializes the query the same way, this just keeps the same error for `Opts`
+        // that can't be represented
+        let _ = opts.to_query_string()?;
+        let mut request = self.session.client.get(self.fetcher.uri()).query(opts);
+        if let Some(request_timeout) = policy.request_timeout {
+            request = request.timeout(request_timeout);
+        }
+
+        crate::types::NaiveResponse::from_async_response(
+            request.send().await,
+            policy.max_response_bytes,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use crate::mock;
+
+    const FREE_LIMIT: usize = crate::opts::FREE_LIMIT as usize;
+    const PREMIUM_LIMIT: usize = crate::opts::PREMIUM_LIMIT as usize;
+
+    #[tokio::test]
+    async fn keyless() {
+        let mut fetcher = AsyncSession::new().fetcher(Opts::default());
+
+        let single = fetcher.try_get(1).await.unwrap();
+        let triple = fetcher.try_get(3).await.unwrap();
+        let the_rest = fetcher.drain();
+
+        assert_eq!(single.len(), 1);
+        assert_eq!(triple.len(), 3);
+        assert_eq!(FREE_LIMIT, single.len() + triple.len() + the_rest.len());
+    }
+
+    #[tokio::test]
+    async fn api_key() {
+        let session = AsyncSession::new();
+        let mut fetcher = session.fetcher(Opts::builder().api_key("<key>".to_string()).build());
+
+        // Premium requests are never delayed
+        let start = Instant::now();
+        assert_eq!(fetcher.try_get(PREMIUM_LIMIT * 2).await.unwrap().len(), 40);
+        assert!(start.elapsed() < constants::DELAY);
+    }
+
+    #[tokio::test]
+    async fn delays() {
+        let session = AsyncSession::new();
+        let mut first = session.fetcher(Opts::default());
+        let mut second = session.fetcher(Opts::default());
+
+        // Fetchers from the same session coordinate their delays even when run concurrently
+        let start = Instant::now();
+        let (first, second) = tokio::join!(first.try_get(FREE_LIMIT), second.try_get(FREE_LIMIT));
+        assert_eq!(first.unwrap().len(), FREE_LIMIT);
+        assert_eq!(second.unwrap().len(), FREE_LIMIT);
+        let elapsed = start.elapsed();
+        assert!(elapsed >= constants::DELAY, "{:?}", elapsed);
+        assert!(elapsed < constants::DELAY * 2, "{:?}", elapsed);
+
+        // While a separate session doesn't need to wait
+        let start = Instant::now();
+        AsyncSession::new()
+            .fetcher(Opts::default())
+            .try_get(1)
+            .await
+            .unwrap();
+        assert!(start.elapsed() < constants::DELAY);
+    }
+
+    #[tokio::test]
+    async fn validated() {
+        let session = AsyncSession::new();
+        let mut fetcher = session.fetcher(Opts::default());
+        let even_port = |proxy: Proxy| async move {
+            // Give the validations a chance to overlap
+            time::sleep(Duration::from_millis(50)).await;
+            proxy.socket.port().is_multiple_of(2)
+        };
+
+        // Only two of the default page are approved of so this takes two requests
+        let start = Instant::now();
+        let proxies = fetcher.try_get_async_validated(3, even_port).await.unwrap();
+        assert_eq!(proxies.len(), 3);
+        assert!(proxies
+            .iter()
+            .all(|proxy| proxy.socket.port().is_multiple_of(2)));
+        // The validations ran concurrently instead of one after the other
+        assert!(start.elapsed() < constants::DELAY + Duration::from_millis(50 * 5));
+
+        // Rejecting everything eventually gives up
+        let mut fetcher = session.fetcher(Opts::builder().api_key("<key>".to_string()).build());
+        assert!(matches!(
+            fetcher
+                .try_get_async_validated(1, |_| async { false })
+                .await,
+            Err(ApiError::AttemptsExhausted { attempts }) if attempts == constants::MAX_ATTEMPTS
+        ));
+    }
+
+    #[tokio::test]
+    async fn errors() {
+        let mut fetcher = AsyncSession::new().fetcher(Opts::default());
+
+        mock::push_response(200, "No proxy");
+        assert!(matches!(fetcher.try_get(1).await, Err(ApiError::NoProxy)));
+
+        // The response is handled the same as the blocking version
+        mock::push_response(429, "Too Many Requests");
+        assert!(matches!(fetcher.try_get(1).await, Err(ApiError::RateLimit)));
+
+        // `Retry-After` is waited out without blocking
+        mock::push_retry_after(Duration::from_millis(10));
+        assert_eq!(fetcher.try_get(1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn filtering() {
+        let session = AsyncSession::new().with_daily_limit(3);
+        let mut fetcher = session.fetcher(Opts::default());
+        let proxy = mock::default_proxy();
+        let other = Proxy {
+            socket: "1.2.3.4:1".parse().unwrap(),
+            ..mock::default_proxy()
+        };
+
+        // Asking for nothing doesn't request anything
+        assert!(fetcher.try_get(0).await.unwrap().is_empty());
+        assert_eq!(session.requests_today(), 0);
+
+        // Repeats within a page are dropped
+        mock::push_proxies(&[proxy.clone(), proxy.clone(), other.clone()]);
+        let mut proxies = fetcher.try_get(2).await.unwrap();
+        proxies.sort_by_key(|proxy| proxy.socket);
+        assert_eq!(proxies, [other.clone(), proxy.clone()]);
+
+        // Along with reported failures
+        fetcher.report_failure(&proxy);
+        mock::push_proxies(&[proxy, other]);
+        assert_eq!(fetcher.try_get(1).await.unwrap().len(), 1);
+        assert_eq!(session.requests_today(), 2);
+
+        // And the daily limit is kept to without requesting
+        fetcher.try_get(FREE_LIMIT).await.unwrap();
+        assert!(matches!(
+            fetcher.try_get(1).await,
+            Err(ApiError::DailyLimitReached { limit: 3 })
+        ));
+        assert_eq!(session.requests_today(), 3);
+    }
+
+    #[cfg(feature = "log")]
+    #[tokio::test]
+    async fn logging() {
+        mock::init_logger();
+        let logged = |needle: &str| mock::logs().iter().any(|log| log.contains(needle));
+        let policy = FetchPolicy::builder()
+            .max_retries(1)
+            .retry_backoff(Duration::from_millis(10))
+            .build();
+        let session = AsyncSession::new()
+            .with_uri("http://async-logging.test/api?")
+            .with_policy(policy);
+
+        // The api key is redacted from the logged url, and retries are logged along the way
+        let mut fetcher = session.fetcher(Opts::builder().api_key("hunter3".to_string()).build());
+        mock::push_response(500, "Internal Server Error");
+        fetcher.try_get(1).await.unwrap();
+        assert!(logged(
+            "DEBUG Requesting http://async-logging.test/api?api=%3Credacted%3E"
+        ));
+        assert!(logged("DEBUG Retrying after 10ms"));
+        assert!(logged(
+            "DEBUG Got 20 proxies from http://async-logging.test/api?"
+        ));
+        assert!(!logged("hunter3"));
+
+        // and keyless requests log how long they wait
+        let mut fetcher = session.fetcher(Opts::default());
+        fetcher.try_get(FREE_LIMIT + 1).await.unwrap();
+        assert!(logged("DEBUG Waiting"));
+    }
+
+    #[tokio::test]
+    async fn exhausted() {
+        let mut fetcher = AsyncSession::new().fetcher(Opts::default());
+
+        // An empty page stops the fetcher instead of requesting forever
+        mock::push_proxies(&[]);
+        assert!(matches!(fetcher.try_get(1).await, Err(ApiError::Exhausted)));
+    }
+}
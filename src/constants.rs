@@ -2,7 +2,29 @@ use std::time::Duration;
 
 // Note: pubproxy doesn't support https
 pub const API_URI: &str = "http://pubproxy.com/api/proxy?";
+// A full premium response is only a few KiB so this is very generous
+pub const MAX_RESPONSE_BYTES: usize = 1024 * 1024;
 pub const REPO_URI: &str = env!("CARGO_PKG_REPOSITORY");
+pub const API_KEY_VAR: &str = "PUBPROXY_API_KEY";
+
+// How many requests can be made when searching for specific proxies before giving up
+pub const MAX_ATTEMPTS: usize = 5;
+// How many times a `Retry-After` will be honored for a single request before giving up
+pub const MAX_RETRY_AFTER: usize = 3;
+// Transient failures are returned right away unless retries are asked for
+pub const MAX_RETRIES: usize = 0;
+// How long the first retry of a transient failure waits, doubling with each retry after
+pub const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+// How many user provided validations are run at once
+#[cfg(feature = "async")]
+pub const MAX_CONCURRENT_VALIDATIONS: usize = 8;
+
+// How long the TCP connect check for `Fetcher::try_get_validated` can take per proxy
+pub const VALIDATION_TIMEOUT: Duration = Duration::from_secs(5);
+
+// How long a proxy is skipped for after being reported as failing
+pub const FAILURE_TTL: Duration = Duration::from_secs(10 * 60);
 
 // Note: A shorter delay is used when testing
 pub const DELAY: Duration = Duration::from_millis(if cfg!(test) { 100 } else { 1_100 });
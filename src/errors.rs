@@ -5,8 +5,9 @@
 
 use std::fmt;
 
-use crate::{constants, types::NaiveResponse};
+use crate::types::{NaiveResponse, Protocol};
 
+use iso_country::Country;
 use thiserror::Error;
 
 /// Represents an error with a parameter type.
@@ -26,6 +27,56 @@ impl<T: PartialEq + fmt::Debug> ParamError<T> {
     }
 }
 
+/// Represents [`Opts`][crate::opts::Opts] that couldn't be turned into a query string.
+#[derive(Error, Debug, PartialEq)]
+pub enum OptsError {
+    #[error("The `{field}` option can't be represented in a url: {reason}")]
+    Unrepresentable { field: String, reason: String },
+}
+
+/// Represents an API key that couldn't be read from an environment variable with
+/// [`OptsBuilder::api_key_from_env`][crate::opts::OptsBuilder::api_key_from_env].
+#[derive(Error, Debug, PartialEq)]
+pub enum ApiKeyError {
+    #[error("The `{0}` environment variable isn't set")]
+    Missing(String),
+
+    #[error("The `{0}` environment variable is empty")]
+    Empty(String),
+
+    #[error("The `{0}` environment variable isn't valid unicode")]
+    NotUnicode(String),
+}
+
+/// Represents a string that isn't the name of any variant of a type like
+/// [`Level`][crate::types::Level] or [`Protocol`][Protocol].
+#[derive(Error, Debug, PartialEq)]
+#[error("'{value}' isn't a valid {kind}")]
+pub struct UnknownName {
+    pub kind: &'static str,
+    pub value: String,
+}
+
+/// Represents a country code or name that couldn't be used in
+/// [`Countries`][crate::types::Countries].
+#[derive(Error, Debug, PartialEq)]
+pub enum CountryError {
+    #[error("'{0}' isn't a valid ISO 3166-1 alpha-2 country code")]
+    InvalidCode(String),
+    #[error("'{0}' isn't the name of any country")]
+    UnknownName(String),
+    #[error("'{name}' could be any of {matches:?}")]
+    AmbiguousName { name: String, matches: Vec<Country> },
+}
+
+/// Represents a [`Proxy`][crate::proxy::Proxy] whose protocol can't be used with a client.
+///
+/// For instance the `hyper` connectors only handle HTTP and SOCKS5 proxies, so a SOCKS4 proxy
+/// will return this error.
+#[derive(Error, Debug, PartialEq)]
+#[error("{0:?} proxies aren't supported by this client")]
+pub struct UnsupportedProtocol(pub Protocol);
+
 /// Represents all possible errors returned by the API.
 ///
 /// Some variants should be entirely prevented by this library like `Client`, while others are
@@ -41,42 +92,90 @@ pub enum ApiError {
     #[error("Invalid API key, make sure your key is valid")]
     ApiKey,
 
-    // TODO: mention fetchers from multiple sessions
     #[error(
-        "You have exceeded the rate limit. This could be due to multiple programs using the API. \
- If this is not the case then sorry but the API hates you, consider raising an issue."
+        "You have exceeded the rate limit. This could be due to multiple programs or sessions \
+ using the API. If this is not the case then sorry but the API hates you, consider raising an \
+ issue."
     )]
     RateLimit,
 
     #[error("You have exhausted the daily limit of proxies.")]
     DailyLimit,
 
+    #[error("You have reached the configured daily limit of {limit} requests.")]
+    DailyLimitReached { limit: usize },
+
     #[error("No matching proxies, consider broadening the parameters used")]
     NoProxy,
 
+    #[error("The API stopped returning new proxies, consider broadening the parameters used")]
+    Exhausted,
+
+    #[error("Couldn't find the requested proxies within {attempts} requests")]
+    AttemptsExhausted { attempts: usize },
+
+    #[error("Couldn't find enough proxies in {country:?} within {attempts} requests")]
+    CountryExhausted { country: Country, attempts: usize },
+
+    #[error("None of the weights were positive so there's nothing to split the proxies between")]
+    NoPositiveWeight,
+
+    #[error(transparent)]
+    Opts(#[from] OptsError),
+
+    #[error("The request to the API timed out")]
+    Timeout,
+
+    #[error("The API response was larger than the maximum of {limit} bytes")]
+    ResponseTooLarge { limit: usize },
+
+    // The raw body is kept around to help with figuring out what the API changed
+    #[error("Couldn't parse the API's response: {source}")]
+    Parse {
+        body: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
     #[error("The API returned an unexpected message. Consider raising an issue with the library")]
     Unknown,
 }
 
+impl ApiError {
+    /// If the error is likely to go away by itself, like a timeout or an internal server error, so
+    /// the request is worth retrying. Errors like an invalid API key will never succeed on a retry.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Timeout | Self::Server { .. })
+    }
+
+    /// Internal
+    pub(crate) fn unparsable(naive_resp: NaiveResponse, source: serde_json::Error) -> Self {
+        // Known errors can still come back with a successful status
+        let body = naive_resp.text.clone();
+        match Self::from(naive_resp) {
+            Self::Unknown => Self::Parse { body, source },
+            err => err,
+        }
+    }
+}
+
 impl From<NaiveResponse> for ApiError {
     fn from(naive_resp: NaiveResponse) -> Self {
-        let NaiveResponse { status, text } = naive_resp;
+        let NaiveResponse { status, text, .. } = naive_resp;
 
         // Some known errors get returned with varied `status` codes so match on response text first
         // then add context to unknown status codes
         match Self::from(text.clone()) {
             Self::Unknown => {
-                if (400..500).contains(&status) {
+                if status == 429 {
+                    // Even without a `Retry-After` this is still just the rate limit
+                    Self::RateLimit
+                } else if (400..500).contains(&status) {
                     Self::Client { status, text }
                 } else if (500..600).contains(&status) {
                     Self::Server { status, text }
                 } else {
-                    unreachable!(
-                        "Tried creating ApiError from valid response ({}). Please raise an issue \
-                         at {}.",
-                        status,
-                        constants::REPO_URI
-                    );
+                    Self::Unknown
                 }
             }
             err => err,
@@ -0,0 +1,264 @@
+//! [`ProxyFeed`][ProxyFeed] keeps a continuously updated view of recently fetched proxies.
+
+use std::{
+    collections::VecDeque,
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{constants, errors::ApiError, fetcher::Fetcher, proxy::Proxy};
+
+/// Internal
+#[derive(Debug)]
+struct Ring {
+    capacity: usize,
+    proxies: VecDeque<Proxy>,
+}
+
+impl Ring {
+    fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            capacity: capacity.get(),
+            proxies: VecDeque::with_capacity(capacity.get()),
+        }
+    }
+
+    // Adds `proxies` evicting the oldest entries to stay within the capacity
+    fn extend(&mut self, proxies: Vec<Proxy>) {
+        for proxy in proxies {
+            if self.proxies.len() == self.capacity {
+                self.proxies.pop_front();
+            }
+            self.proxies.push_back(proxy);
+        }
+    }
+}
+
+type Worker = JoinHandle<(Fetcher, Result<(), ApiError>)>;
+
+/// A background feed of the most recently fetched proxies.
+///
+/// Once started a `ProxyFeed` continuously fetches proxies with its
+/// [`Fetcher`][crate::fetcher::Fetcher] on a background thread, keeping the latest `capacity`
+/// proxies in a ring buffer where the oldest proxies are evicted as new ones arrive. The feed
+/// waits for an interval between fetches on top of the usual delays from the `Fetcher`'s
+/// [`Session`][crate::session::Session], so it won't cause rate-limiting by itself. This is
+/// intended for things like dashboards that just want a view of what's currently available.
+///
+/// ```no_run
+/// use lead_oxide::{feed::ProxyFeed, fetcher::Fetcher};
+/// use std::{num::NonZeroUsize, thread, time::Duration};
+///
+/// let mut feed = ProxyFeed::new(Fetcher::default(), NonZeroUsize::new(50).unwrap());
+/// feed.start();
+/// thread::sleep(Duration::from_secs(10));
+/// println!("Currently available: {:#?}", feed.snapshot());
+/// feed.stop().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ProxyFeed {
+    fetcher: Option<Fetcher>,
+    ring: Arc<Mutex<Ring>>,
+    running: Arc<AtomicBool>,
+    interval: Duration,
+    worker: Option<Worker>,
+}
+
+impl ProxyFeed {
+    /// Creates a stopped `ProxyFeed` that fetches with `fetcher` and keeps at most `capacity`
+    /// proxies.
+    pub fn new(fetcher: Fetcher, capacity: NonZeroUsize) -> Self {
+        Self {
+            fetcher: Some(fetcher),
+            ring: Arc::new(Mutex::new(Ring::new(capacity))),
+            running: Arc::new(AtomicBool::new(false)),
+            interval: constants::DELAY,
+            worker: None,
+        }
+    }
+
+    /// Sets how long to wait between fetches. This defaults to the same delay that is used to
+    /// avoid rate-limiting on the keyless API. This takes effect the next time the feed is started.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Starts fetching in the background. This does nothing if the feed is already running. If a
+    /// previous run stopped from an error that wasn't returned by [`stop`][ProxyFeed::stop] then
+    /// that error is discarded.
+    pub fn start(&mut self) {
+        if self.is_running() {
+            return;
+        }
+
+        let _ = self.join();
+        let mut fetcher = self
+            .fetcher
+            .take()
+            .expect("The fetcher is always returned when the feed stops");
+        let ring = Arc::clone(&self.ring);
+        let running = Arc::clone(&self.running);
+        let interval = self.interval;
+
+        self.running.store(true, Ordering::SeqCst);
+        self.worker = Some(thread::spawn(move || {
+            while running.load(Ordering::SeqCst) {
+                match fetcher.try_get(1) {
+                    Ok(mut proxies) => {
+                        // Keep the page in the order it was returned
+                        let mut page = fetcher.take_buffered();
+                        page.append(&mut proxies);
+                        lock(&ring).extend(page);
+                    }
+                    Err(err) => {
+                        running.store(false, Ordering::SeqCst);
+                        return (fetcher, Err(err));
+                    }
+                }
+
+                thread::park_timeout(interval);
+            }
+
+            (fetcher, Ok(()))
+        }));
+    }
+
+    /// Stops fetching in the background, blocking until any in-progress fetch finishes. If the feed
+    /// stopped on its own because the API returned an error then that error is returned here.
+    pub fn stop(&mut self) -> Result<(), ApiError> {
+        self.running.store(false, Ordering::SeqCst);
+        self.join()
+    }
+
+    /// If the feed is currently fetching in the background.
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Returns the proxies currently in the feed ordered from oldest to newest.
+    pub fn snapshot(&self) -> Vec<Proxy> {
+        lock(&self.ring).proxies.iter().cloned().collect()
+    }
+
+    fn join(&mut self) -> Result<(), ApiError> {
+        match self.worker.take() {
+            Some(worker) => {
+                // Wake the worker up if it's waiting out the interval
+                worker.thread().unpark();
+                let (fetcher, result) = worker.join().expect("The background fetch loop panicked");
+                self.fetcher = Some(fetcher);
+                result
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for ProxyFeed {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(worker) = self.worker.take() {
+            worker.thread().unpark();
+            // Don't panic from a panicked worker while dropping
+            let _ = worker.join();
+        }
+    }
+}
+
+fn lock(ring: &Mutex<Ring>) -> MutexGuard<'_, Ring> {
+    // The ring is always left in a valid state so a poisoned lock is fine to use
+    ring.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{net::SocketAddr, time::Instant};
+
+    use crate::{mock, opts::Opts, session::Session};
+
+    fn capacity(capacity: usize) -> NonZeroUsize {
+        NonZeroUsize::new(capacity).unwrap()
+    }
+
+    #[test]
+    fn ring_evicts_oldest() {
+        let proxy = |port| Proxy {
+            socket: SocketAddr::from(([1, 2, 3, 4], port)),
+            ..mock::default_proxy()
+        };
+        let ports =
+            |ring: &Ring| -> Vec<_> { ring.proxies.iter().map(|p| p.socket.port()).collect() };
+
+        let mut ring = Ring::new(capacity(3));
+        ring.extend(vec![proxy(1), proxy(2)]);
+        assert_eq!(ports(&ring), vec![1, 2]);
+
+        // Adding more than the capacity keeps only the most recent
+        ring.extend(vec![proxy(3), proxy(4), proxy(5), proxy(6)]);
+        assert_eq!(ports(&ring), vec![4, 5, 6]);
+    }
+
+    #[test]
+    fn stays_bounded() {
+        let session = Session::new();
+        let mut feed = ProxyFeed::new(session.fetcher(Opts::default()), capacity(7))
+            .with_interval(Duration::from_secs(0));
+        assert!(!feed.is_running());
+        assert!(feed.snapshot().is_empty());
+
+        // Every fetch returns a page of 5, so wait for a few fetches worth of proxies
+        feed.start();
+        assert!(feed.is_running());
+        let start = Instant::now();
+        while session.requests_today() < 3 {
+            assert!(start.elapsed() < Duration::from_secs(5), "The feed stalled");
+            thread::sleep(Duration::from_millis(10));
+        }
+        feed.stop().unwrap();
+        assert!(!feed.is_running());
+
+        // Only the newest proxies are kept
+        let fetched: Vec<_> = (0..session.requests_today())
+            .flat_map(|nth| mock::nth_page(nth, 5))
+            .collect();
+        assert_eq!(feed.snapshot(), &fetched[fetched.len() - 7..]);
+
+        // And it can be started back up again
+        feed.start();
+        assert!(feed.is_running());
+        feed.stop().unwrap();
+    }
+
+    #[test]
+    fn stops_on_error() {
+        let session = Session::new().with_daily_limit(2);
+        let mut feed = ProxyFeed::new(session.fetcher(Opts::default()), capacity(20))
+            .with_interval(Duration::from_secs(0));
+
+        // The feed stops on its own once the daily limit is hit
+        feed.start();
+        let start = Instant::now();
+        while feed.is_running() {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "The feed never stopped"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(matches!(
+            feed.stop(),
+            Err(ApiError::DailyLimitReached { limit: 2 })
+        ));
+        // The proxies from before the error are still kept
+        assert_eq!(feed.snapshot().len(), 10);
+    }
+}
@@ -4,22 +4,177 @@
 //! default will return any proxies on the listing.
 
 use std::{
-    sync::{Arc, Mutex},
-    thread,
-    time::Instant,
+    cmp::{Ordering, Reverse},
+    collections::{HashMap, HashSet, VecDeque},
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    net::SocketAddr,
+    num::NonZeroU32,
+    path::Path,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
 use crate::{
     constants,
     errors::ApiError,
-    opts::Opts,
-    proxy::{proxies_from_json, Proxy},
-    types::NaiveResponse,
+    opts::{Limit, Opts},
+    policy::FetchPolicy,
+    proxy::{proxies_from_json, Proxy, QualityWeights, SupportsFilter},
+    session::{Session, GLOBAL},
+    throttle::OutputRate,
+    types::{Countries, Level, NaiveResponse},
 };
 
-lazy_static! {
-    static ref LAST_FETCHED: Arc<Mutex<Instant>> =
-        Arc::new(Mutex::new(Instant::now() - constants::DELAY));
+use chrono::Utc;
+use iso_country::Country;
+use serde::Serialize;
+
+/// A point-in-time view of a [`Fetcher`][Fetcher]'s state.
+///
+/// This is returned by [`Fetcher::snapshot`][Fetcher::snapshot] and is mainly meant to be
+/// serialized and attached to bug reports, so any API key in the `opts` is redacted.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct FetcherSnapshot {
+    /// The `Opts` used by the `Fetcher` in the same format they are sent to the API.
+    pub opts: Opts,
+    /// The number of proxies in the internal list.
+    pub buffered: usize,
+    /// How long until the `Fetcher` can make a request without being delayed.
+    pub time_until_ready: Duration,
+    /// The number of requests made by the `Fetcher`'s `Session` within the last 24 hours.
+    pub requests_today: usize,
+}
+
+/// A single page of proxies returned by [`Fetcher::fetch_page`][Fetcher::fetch_page].
+///
+/// The API doesn't have any paging of its own, so the offsets are synthesized from the number of
+/// proxies the `Fetcher` has returned as pages so far.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Page {
+    /// The proxies in this page.
+    pub proxies: Vec<Proxy>,
+    /// The offset of the first proxy in this page.
+    pub offset: usize,
+    /// The offset of the next page, or `None` if this page wasn't full meaning the API likely
+    /// doesn't have any more matching proxies.
+    pub next: Option<usize>,
+}
+
+impl Page {
+    /// If more proxies are likely available from the API.
+    pub fn has_more(&self) -> bool {
+        self.next.is_some()
+    }
+}
+
+/// How well the proxies from [`Fetcher::try_get_verbose`][Fetcher::try_get_verbose] honored each
+/// of the `Fetcher`'s [`Opts`][crate::opts::Opts].
+///
+/// The API's filtering isn't perfect, so this can be used to see how much a set of options can be
+/// trusted.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FilterReport {
+    /// The number of proxies that were checked.
+    pub checked: usize,
+    /// How each option that was set fared, in the same order as
+    /// [`Opts::describe`][crate::opts::Opts::describe].
+    pub options: Vec<OptionReport>,
+}
+
+impl FilterReport {
+    fn new(opts: &Opts, proxies: &[Proxy]) -> Self {
+        let mut options: Vec<OptionReport> = Vec::new();
+        for proxy in proxies {
+            for (i, (name, matched)) in opts.matches(proxy).into_iter().enumerate() {
+                if options.len() == i {
+                    options.push(OptionReport {
+                        name,
+                        matched: 0,
+                        violated: 0,
+                    });
+                }
+
+                if matched {
+                    options[i].matched += 1;
+                } else {
+                    options[i].violated += 1;
+                }
+            }
+        }
+
+        Self {
+            checked: proxies.len(),
+            options,
+        }
+    }
+
+    /// The number of proxies that violated the option with the given `name`. Options that weren't
+    /// set are never violated.
+    pub fn violations(&self, name: &str) -> usize {
+        self.options
+            .iter()
+            .find(|option| option.name == name)
+            .map_or(0, |option| option.violated)
+    }
+
+    /// If every proxy honored every option.
+    pub fn is_clean(&self) -> bool {
+        self.options.iter().all(|option| option.violated == 0)
+    }
+}
+
+/// How many proxies matched or violated a single option in a [`FilterReport`][FilterReport].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OptionReport {
+    /// The name of the option as listed by [`Opts::describe`][crate::opts::Opts::describe].
+    pub name: &'static str,
+    /// The number of proxies that honored the option.
+    pub matched: usize,
+    /// The number of proxies that didn't.
+    pub violated: usize,
+}
+
+/// How proxies that are listed more than once in a single response from the API are handled.
+///
+/// The API can return the same endpoint several times within one page. This only affects
+/// duplicates within each response and is set with
+/// [`Fetcher::with_intra_page_dedup`][Fetcher::with_intra_page_dedup].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IntraPageDedup {
+    /// Only keeps the first proxy for each socket in a response.
+    #[default]
+    Dedup,
+    /// Keeps every proxy exactly as the API returned them.
+    Keep,
+}
+
+/// Internal
+///
+/// What [`Fetcher::classify`][Fetcher::classify] decided to do after a request
+#[derive(Debug)]
+pub(crate) enum Step {
+    /// Wait this long and then request again
+    Retry(Duration),
+    /// The final result of the request, along with the raw response the proxies came from
+    Done(Result<(Vec<Proxy>, String), ApiError>),
+}
+
+/// Internal
+#[derive(Debug)]
+struct Prefetch {
+    threshold: usize,
+    pending: Option<JoinHandle<Vec<Proxy>>>,
+}
+
+// An in-flight prefetch belongs to the original `Fetcher` so clones start without one
+impl Clone for Prefetch {
+    fn clone(&self) -> Self {
+        Self {
+            threshold: self.threshold,
+            pending: None,
+        }
+    }
 }
 
 /// The entrypoint into the API.
@@ -30,13 +185,30 @@ lazy_static! {
 #[derive(Clone, Debug)]
 pub struct Fetcher {
     opts: Opts,
+    session: Session,
     proxies: Vec<Proxy>,
+    output_rate: Option<OutputRate>,
+    seeds: Vec<Proxy>,
+    page_offset: usize,
+    failures: HashMap<SocketAddr, Instant>,
+    failure_ttl: Duration,
+    validation_timeout: Duration,
+    policy: FetchPolicy,
+    uri: String,
+    keep_raw: bool,
+    last_raw_response: Option<String>,
+    intra_page_dedup: IntraPageDedup,
+    buffer_dedup: bool,
+    quality_weights: QualityWeights,
+    prefetch: Option<Prefetch>,
+    result_interleave: bool,
+    force_keyless: bool,
 }
 
 impl Fetcher {
-    /// Creates a new `Fetcher` with a given set of `Opts`. If you don't care about any attributes
-    /// of the returned proxies then `Fetcher::default` is a convenience method for
-    /// `Fetcher::new(Opts::default())`.
+    /// Creates a new `Fetcher` with a given set of `Opts` that uses the global
+    /// [`Session`][crate::session::Session]. If you don't care about any attributes of the returned
+    /// proxies then `Fetcher::default` is a convenience method for `Fetcher::new(Opts::default())`.
     ///
     /// ```
     /// use lead_oxide::{opts::Opts, fetcher::Fetcher, types::{Level, Protocol}};
@@ -51,16 +223,300 @@ impl Fetcher {
     /// );
     /// ```
     pub fn new(opts: Opts) -> Self {
+        Self::with_session(opts, GLOBAL.clone())
+    }
+
+    /// Internal
+    pub(crate) fn with_session(opts: Opts, session: Session) -> Self {
+        let policy = session.policy().clone();
+        let uri = session.uri().to_string();
         Self {
             opts,
+            session,
             proxies: Vec::new(),
+            output_rate: None,
+            seeds: Vec::new(),
+            page_offset: 0,
+            failures: HashMap::new(),
+            failure_ttl: constants::FAILURE_TTL,
+            validation_timeout: constants::VALIDATION_TIMEOUT,
+            policy,
+            uri,
+            keep_raw: false,
+            last_raw_response: None,
+            intra_page_dedup: IntraPageDedup::default(),
+            buffer_dedup: true,
+            quality_weights: QualityWeights::default(),
+            prefetch: None,
+            result_interleave: false,
+            force_keyless: false,
+        }
+    }
+
+    /// Sets the maximum size of a response body that will be read from the API. Any larger
+    /// responses will return an [`ApiError::ResponseTooLarge`][ApiError::ResponseTooLarge] instead
+    /// of being read into memory. This defaults to 1 MiB which is far larger than any legitimate
+    /// response, so this is mostly useful when pointing at an untrusted mirror.
+    pub fn with_max_response_bytes(mut self, max_response_bytes: usize) -> Self {
+        self.policy.max_response_bytes = max_response_bytes;
+        self
+    }
+
+    /// Limits how long a single request to the API can take before returning an
+    /// [`ApiError::Timeout`][ApiError::Timeout]. This only covers the network call itself and is
+    /// entirely separate from the delay between keyless requests, so a slow API can be told apart
+    /// from waiting out the rate-limit. By default there is no timeout.
+    pub fn with_request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.policy.request_timeout = Some(request_timeout);
+        self
+    }
+
+    /// Uses `policy` for all the knobs that control how requests are made, replacing any that were
+    /// set with the individual methods like [`with_request_timeout`][Fetcher::with_request_timeout].
+    /// This defaults to the policy of the `Fetcher`'s [`Session`][crate::session::Session].
+    ///
+    /// ```
+    /// use lead_oxide::{fetcher::Fetcher, policy::FetchPolicy};
+    ///
+    /// let fetcher = Fetcher::default().with_policy(FetchPolicy::builder().max_attempts(10).build());
+    /// ```
+    pub fn with_policy(mut self, policy: FetchPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sends this `Fetcher`'s requests to `uri` instead of the one from its
+    /// [`Session`][crate::session::Session::with_uri], which defaults to pubproxy.com.
+    pub fn with_uri(mut self, uri: impl Into<String>) -> Self {
+        self.uri = uri.into();
+        self
+    }
+
+    /// Makes a `Fetcher` with an API key behave like it doesn't have one. Requests wait out the
+    /// usual delays and only ask for a keyless page worth of proxies, while the key is still sent
+    /// along with the requests. This only changes how fetching is paced, so
+    /// [`Opts::is_premium`][crate::opts::Opts::is_premium] is still `true`. Useful for testing the
+    /// delayed path or for deliberately throttling premium usage.
+    ///
+    /// ```
+    /// use lead_oxide::{fetcher::Fetcher, opts::Opts};
+    ///
+    /// let mut fetcher = Fetcher::new(Opts::builder().api_key("<key>".to_string()).build());
+    /// fetcher.force_keyless();
+    /// ```
+    pub fn force_keyless(&mut self) {
+        self.force_keyless = true;
+        self.opts.limit = Limit::Free;
+    }
+
+    /// The [`FetchPolicy`][crate::policy::FetchPolicy] currently used by the `Fetcher`.
+    pub fn policy(&self) -> &FetchPolicy {
+        &self.policy
+    }
+
+    /// Keeps the body of the most recent successful response from the API around so that it can be
+    /// accessed with [`last_raw_response`][Fetcher::last_raw_response]. This is useful for
+    /// getting at fields that aren't modeled by [`Proxy`][crate::proxy::Proxy]. The body is bounded
+    /// by the same limit as [`with_max_response_bytes`][Fetcher::with_max_response_bytes]. This is
+    /// off by default to avoid holding on to bodies that won't be used.
+    pub fn keep_raw(mut self, keep_raw: bool) -> Self {
+        self.keep_raw = keep_raw;
+        if !keep_raw {
+            self.last_raw_response = None;
+        }
+        self
+    }
+
+    /// Sets how proxies that show up more than once in a single response are handled. By default
+    /// only the first of each is kept, use [`IntraPageDedup::Keep`][IntraPageDedup::Keep] to get
+    /// the responses exactly as the API returned them.
+    pub fn with_intra_page_dedup(mut self, intra_page_dedup: IntraPageDedup) -> Self {
+        self.intra_page_dedup = intra_page_dedup;
+        self
+    }
+
+    /// Sets if proxies are skipped when their socket is already in the internal list. This is on by
+    /// default so that proxies the API keeps listing across requests aren't returned several
+    /// times, turn it off to keep every proxy the API returned.
+    pub fn with_buffer_dedup(mut self, buffer_dedup: bool) -> Self {
+        self.buffer_dedup = buffer_dedup;
+        self
+    }
+
+    /// Sets the weights used to rank proxies in [`try_get_best`][Fetcher::try_get_best]. This
+    /// defaults to [`QualityWeights::default`][crate::proxy::QualityWeights::default].
+    pub fn with_quality_weights(mut self, quality_weights: QualityWeights) -> Self {
+        self.quality_weights = quality_weights;
+        self
+    }
+
+    /// Fetches another page in the background whenever handing out proxies leaves fewer than
+    /// `threshold` in the internal list (including seeds). The prefetch goes through the same
+    /// [`Session`][crate::session::Session] so it still waits out the usual delays, but it means
+    /// bursty consumers usually don't have to wait for a request themselves. The prefetched proxies
+    /// are added to the internal list the next time the `Fetcher` needs more proxies, and any error
+    /// from the prefetch is ignored since that request will just be tried again.
+    ///
+    /// ```no_run
+    /// use lead_oxide::fetcher::Fetcher;
+    ///
+    /// let mut fetcher = Fetcher::default().with_prefetch_below(3);
+    /// // Leaves 1 proxy from the page so another page is fetched in the background
+    /// let proxies = fetcher.try_get(4).unwrap();
+    /// ```
+    pub fn with_prefetch_below(mut self, threshold: usize) -> Self {
+        self.prefetch = Some(Prefetch {
+            threshold,
+            pending: None,
+        });
+        self
+    }
+
+    /// Reorders the proxies returned from [`try_get`][Fetcher::try_get] so that their countries
+    /// alternate as evenly as possible, which gives some geographic spread to anything that pops
+    /// proxies off the front. The countries are still whatever the API returned, this only changes
+    /// the order. This is off by default.
+    pub fn with_result_interleave(mut self, result_interleave: bool) -> Self {
+        self.result_interleave = result_interleave;
+        self
+    }
+
+    /// Limits how quickly proxies are returned to at most `count` proxies `per` the provided
+    /// duration. Methods that return proxies will block until returning them wouldn't exceed the
+    /// rate. This is independent of the delay used to avoid the API's rate-limiting, and is
+    /// intended for pacing your own usage of the proxies.
+    ///
+    /// ```
+    /// use lead_oxide::fetcher::Fetcher;
+    /// use std::{num::NonZeroU32, time::Duration};
+    ///
+    /// // At most 10 proxies a minute
+    /// let fetcher = Fetcher::default()
+    ///     .with_output_rate(NonZeroU32::new(10).unwrap(), Duration::from_secs(60));
+    /// ```
+    pub fn with_output_rate(mut self, count: NonZeroU32, per: Duration) -> Self {
+        self.output_rate = Some(OutputRate::new(count, per));
+        self
+    }
+
+    /// Sets how long a proxy is skipped for after being passed to
+    /// [`report_failure`][Fetcher::report_failure]. This defaults to 10 minutes.
+    pub fn with_failure_ttl(mut self, failure_ttl: Duration) -> Self {
+        self.failure_ttl = failure_ttl;
+        self
+    }
+
+    /// Sets how long each connection attempt from
+    /// [`try_get_validated`][Fetcher::try_get_validated] can take before the proxy is considered
+    /// dead. This defaults to 5 seconds.
+    pub fn with_validation_timeout(mut self, validation_timeout: Duration) -> Self {
+        self.validation_timeout = validation_timeout;
+        self
+    }
+
+    /// Adds your own `proxies` which are served in the order given before any proxies from the API.
+    /// The API is only requested once all the seeds have been used up, which allows for mixing in
+    /// proxies you already trust. Any seeds that were reported with
+    /// [`report_failure`][Fetcher::report_failure] are skipped, so it's also possible to validate
+    /// proxies before they are handed out by reporting the ones that fail a check.
+    ///
+    /// ```
+    /// use lead_oxide::{fetcher::Fetcher, proxy::Proxy};
+    ///
+    /// let trusted = Proxy::builder().socket("1.2.3.4:8080".parse().unwrap()).build();
+    /// let mut fetcher = Fetcher::default();
+    /// fetcher.prefer_seeds(vec![trusted.clone()]);
+    /// // The seed is used without making any requests
+    /// assert_eq!(fetcher.try_get(1).unwrap(), vec![trusted]);
+    /// ```
+    pub fn prefer_seeds(&mut self, proxies: Vec<Proxy>) {
+        let mut proxies = self.eligible(proxies, Instant::now());
+        self.seeds.append(&mut proxies);
+    }
+
+    /// Reports that `proxy` failed, so any proxy with the same socket is skipped by this
+    /// `Fetcher` until the failure TTL has passed. This removes matching proxies from the internal
+    /// list and filters them out of any responses from the API in the meantime.
+    ///
+    /// ```no_run
+    /// use lead_oxide::fetcher::Fetcher;
+    ///
+    /// let mut fetcher = Fetcher::default();
+    /// let proxy = fetcher.try_get(1).unwrap().remove(0);
+    /// // ... the proxy turns out to be dead
+    /// fetcher.report_failure(&proxy);
+    /// ```
+    pub fn report_failure(&mut self, proxy: &Proxy) {
+        self.report_failure_at(proxy, Instant::now());
+    }
+
+    fn report_failure_at(&mut self, proxy: &Proxy, now: Instant) {
+        // Clear out any expired failures while we're here
+        self.failures.retain(|_, expires| *expires > now);
+        self.failures.insert(proxy.socket, now + self.failure_ttl);
+        let proxies = std::mem::take(&mut self.proxies);
+        self.proxies = self.eligible(proxies, now);
+        let seeds = std::mem::take(&mut self.seeds);
+        self.seeds = self.eligible(seeds, now);
+    }
+
+    // Removes repeated sockets from a single response depending on the `IntraPageDedup` policy
+    fn dedup_page(&self, mut proxies: Vec<Proxy>) -> Vec<Proxy> {
+        if self.intra_page_dedup == IntraPageDedup::Dedup {
+            let mut seen = HashSet::with_capacity(proxies.len());
+            proxies.retain(|proxy| seen.insert(proxy.socket));
+        }
+
+        proxies
+    }
+
+    // Filters out any proxies whose socket is already in the internal list or seeds
+    fn dedup_buffered(&self, mut proxies: Vec<Proxy>) -> Vec<Proxy> {
+        if self.buffer_dedup {
+            let buffered: HashSet<_> = self
+                .seeds
+                .iter()
+                .chain(&self.proxies)
+                .map(|proxy| proxy.socket)
+                .collect();
+            proxies.retain(|proxy| !buffered.contains(&proxy.socket));
+        }
+
+        proxies
+    }
+
+    // Filters out any proxies that are still within their failure TTL at `now`
+    fn eligible(&self, mut proxies: Vec<Proxy>, now: Instant) -> Vec<Proxy> {
+        if !self.failures.is_empty() {
+            proxies.retain(|proxy| match self.failures.get(&proxy.socket) {
+                Some(expires) => *expires <= now,
+                None => true,
+            });
         }
+
+        proxies
+    }
+
+    /// Internal
+    ///
+    /// Adds the new proxies from a single response to the internal list, dropping any repeated,
+    /// already buffered, or recently failed sockets, and returns how many were added
+    pub(crate) fn buffer_page(&mut self, proxies: Vec<Proxy>) -> usize {
+        let proxies = self.dedup_page(proxies);
+        let proxies = self.eligible(proxies, Instant::now());
+        let mut proxies = self.dedup_buffered(proxies);
+        let added = proxies.len();
+        self.proxies.append(&mut proxies);
+
+        added
     }
 
     /// Attempts to get the specified amount of proxies from the API, returning an error if there
     /// was an issue with the API. Any proxies returned before an error was encountered will still
     /// be stored in the interal buffer, so it's possible to use this to fully exhaust your daily
-    /// limit.
+    /// limit. If a request stops turning up any new proxies then
+    /// [`ApiError::Exhausted`][ApiError::Exhausted] is returned instead of requesting forever.
+    /// Asking for `0` proxies always returns right away without making any requests.
     ///
     /// ```no_run
     /// use lead_oxide::fetcher::Fetcher;
@@ -72,172 +528,1344 @@ impl Fetcher {
     /// let proxies = fetcher.drain();
     /// ```
     pub fn try_get(&mut self, amount: usize) -> Result<Vec<Proxy>, ApiError> {
-        if self.proxies.len() >= amount {
-            // If there's enough in the current list then just go ahead and fulfill without locking
-            Ok(self.proxies.split_off(self.proxies.len() - amount))
-        } else {
-            // Otherwise we need to lock and request the api
-            let mut request = self.request_builder();
-
-            if self.opts.is_premium() {
-                // Don't need to mess with any delays if we're using an api key. (This information
-                // was based off emailing the dev. I never got an api key to test)
-                while self.proxies.len() < amount {
-                    let mut proxies = self.fetch(&mut request)?;
-                    self.proxies.append(&mut proxies);
-                }
-            } else {
-                // If we don't have an api key then we need to coordinate delays to ensure we don't
-                // do more than one request per `constants::DELAY`
-                let mut last_fetched = match LAST_FETCHED.lock() {
-                    Ok(last_fetched) => last_fetched,
-                    Err(err) => {
-                        // If the lock was poisoned then play it safe and reset the timer
-                        let mut poisioned = err.into_inner();
-                        *poisioned = Instant::now();
-                        poisioned
-                    }
-                };
-
-                while self.proxies.len() < amount {
-                    // Delay to prevent rate limiting
-                    let delta = Instant::now().duration_since(*last_fetched);
-                    if delta < constants::DELAY {
-                        thread::sleep(constants::DELAY - delta);
-                    }
-
-                    let mut proxies = self.fetch(&mut request)?;
-                    self.proxies.append(&mut proxies);
+        if amount == 0 {
+            return Ok(Vec::new());
+        }
 
-                    // Update the request time
-                    *last_fetched = Instant::now();
-                }
-            }
+        self.fill(amount.saturating_sub(self.seeds.len()))?;
+        let proxies = self.take(amount);
 
-            Ok(self.proxies.split_off(self.proxies.len() - amount))
+        if self.result_interleave {
+            Ok(interleave(proxies))
+        } else {
+            Ok(proxies)
         }
     }
 
-    fn request_builder(&self) -> ureq::Request {
-        let params = serde_urlencoded::to_string(&self.opts).unwrap_or_else(|_| {
-            panic!(
-                "Failed to serialize url, please raise an issue to address this: {}",
-                constants::REPO_URI
-            )
-        });
-        ureq::get(constants::API_URI).query_str(&params).build()
+    /// Like [`try_get`][Fetcher::try_get], but also returns a [`FilterReport`][FilterReport] of how
+    /// many of the returned proxies actually honored each of the `Fetcher`'s `Opts`.
+    ///
+    /// ```no_run
+    /// use lead_oxide::{fetcher::Fetcher, opts::Opts, types::Level};
+    ///
+    /// let mut fetcher = Fetcher::new(Opts::builder().level(Level::Elite).build());
+    /// let (proxies, report) = fetcher.try_get_verbose(5).unwrap();
+    /// if !report.is_clean() {
+    ///     eprintln!("{} proxies weren't elite", report.violations("level"));
+    /// }
+    /// ```
+    pub fn try_get_verbose(
+        &mut self,
+        amount: usize,
+    ) -> Result<(Vec<Proxy>, FilterReport), ApiError> {
+        let proxies = self.try_get(amount)?;
+        let report = FilterReport::new(&self.opts, &proxies);
+
+        Ok((proxies, report))
     }
 
-    fn fetch(&self, request: &mut ureq::Request) -> Result<Vec<Proxy>, ApiError> {
-        if cfg!(not(test)) {
-            let resp = request.call();
-            let naive_resp = NaiveResponse::from(resp);
+    /// Like [`try_get`][Fetcher::try_get], but the proxies are returned split up by their anonymity
+    /// `level`. Only levels that have proxies get an entry, and how the proxies end up distributed
+    /// depends entirely on what the API returns for the `Fetcher`'s `Opts`, so filtering on a
+    /// `level` means everything ends up in a single bucket.
+    ///
+    /// ```no_run
+    /// use lead_oxide::{fetcher::Fetcher, types::Level};
+    ///
+    /// let mut fetcher = Fetcher::default();
+    /// let by_level = fetcher.try_get_by_level(10).unwrap();
+    /// let sensitive = by_level.get(&Level::Elite);
+    /// let bulk = by_level.get(&Level::Anonymous);
+    /// ```
+    pub fn try_get_by_level(
+        &mut self,
+        amount: usize,
+    ) -> Result<HashMap<Level, Vec<Proxy>>, ApiError> {
+        let mut by_level: HashMap<_, Vec<_>> = HashMap::new();
+        for proxy in self.try_get(amount)? {
+            by_level.entry(proxy.level).or_default().push(proxy);
+        }
 
-            if naive_resp.ok() {
-                proxies_from_json(&naive_resp.text).map_err(|_| ApiError::from(naive_resp))
-            } else {
-                Err(ApiError::from(naive_resp))
-            }
-        } else {
-            use chrono::naive::NaiveDate;
-            use iso_country::Country;
+        Ok(by_level)
+    }
 
-            use crate::{
-                proxy::Supports,
-                types::{Level, Protocol},
-            };
+    /// Like [`try_get`][Fetcher::try_get], but treats `prefer` as a soft preference instead of a
+    /// hard filter. Proxies are still fetched with the `Fetcher`'s own (ideally broader) `Opts`, and
+    /// then the buffered proxies are ranked by how many of the options from `prefer` they match,
+    /// returning the closest matches first. Only the options that are set in `prefer` count
+    /// towards a proxy's score, and proxies that score the same keep their usual order.
+    ///
+    /// ```no_run
+    /// use iso_country::Country;
+    /// use lead_oxide::{fetcher::Fetcher, opts::Opts, types::{Countries, Level}};
+    ///
+    /// let mut fetcher = Fetcher::default();
+    /// // Ideally elite proxies from Canada, but anything will do
+    /// let prefer = Opts::builder()
+    ///     .level(Level::Elite)
+    ///     .countries(Countries::allow().country(Country::CA))
+    ///     .build();
+    /// let proxies = fetcher.try_get_preferring(10, &prefer).unwrap();
+    /// ```
+    pub fn try_get_preferring(
+        &mut self,
+        amount: usize,
+        prefer: &Opts,
+    ) -> Result<Vec<Proxy>, ApiError> {
+        self.fill(amount.saturating_sub(self.seeds.len()))?;
 
-            use std::{
-                iter,
-                net::{Ipv4Addr, SocketAddrV4},
-                time::Duration,
-            };
+        // Seeds get ranked right along with everything else
+        let proxies = self.take_ranked(amount, |proxy| Reverse(prefer.score(proxy)));
 
-            // TODO: is there a better way to mock the api response? It would be nice to test that
-            // errors get interpreted right too. And if we could panic then we can test that the
-            // mutex getting poisoned works right
-            Ok(iter::repeat(Proxy {
-                socket: SocketAddrV4::new(Ipv4Addr::new(1, 2, 3, 4), 4321),
-                country: Country::CA,
-                last_checked: NaiveDate::from_ymd(2020, 1, 1).and_hms(1, 1, 1),
-                level: Level::Anonymous,
-                protocol: Protocol::Http,
-                time_to_connect: Duration::from_secs(21),
-                supports: Supports::default(),
-            })
-            .take(self.opts.limit as usize)
-            .collect())
-        }
+        self.throttle(proxies.len());
+        Ok(proxies)
     }
 
-    /// Consumes the `Fetcher` returning any proxies still left in the internal list.
-    pub fn drain(self) -> Vec<Proxy> {
-        self.proxies
-    }
-}
+    /// Like [`try_get`][Fetcher::try_get], but the proxies are returned fastest first by their
+    /// `time_to_connect`. To have something meaningful to sort, at least a full page of proxies is
+    /// buffered first, so this can make an extra request even when `amount` proxies are already
+    /// on hand. The slower proxies are left in the internal list.
+    ///
+    /// ```no_run
+    /// use lead_oxide::fetcher::Fetcher;
+    ///
+    /// let mut fetcher = Fetcher::default();
+    /// let proxies = fetcher.try_get_fastest(3).unwrap();
+    /// ```
+    pub fn try_get_fastest(&mut self, amount: usize) -> Result<Vec<Proxy>, ApiError> {
+        let pool = amount.max(self.opts.limit as usize);
+        self.fill(pool.saturating_sub(self.seeds.len()))?;
 
-impl Default for Fetcher {
-    fn default() -> Self {
-        Self::new(Opts::default())
+        let proxies = self.take_ranked(amount, |proxy| proxy.time_to_connect);
+
+        self.throttle(proxies.len());
+        Ok(proxies)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use serial_test::serial;
+    /// Like [`try_get`][Fetcher::try_get], but returns the buffered proxies with the highest
+    /// [`quality_score_with`][crate::proxy::Proxy::quality_score_with] using the weights from
+    /// [`with_quality_weights`][Fetcher::with_quality_weights], best first. Only the proxies that
+    /// are already buffered (after fetching enough to cover `amount`) are compared, so this picks
+    /// the best of what's available rather than searching for better proxies.
+    ///
+    /// ```no_run
+    /// use lead_oxide::fetcher::Fetcher;
+    ///
+    /// let mut fetcher = Fetcher::default();
+    /// let proxies = fetcher.try_get_best(3).unwrap();
+    /// ```
+    pub fn try_get_best(&mut self, amount: usize) -> Result<Vec<Proxy>, ApiError> {
+        self.fill(amount.saturating_sub(self.seeds.len()))?;
 
-    use super::*;
+        let now = Utc::now().naive_utc();
+        let weights = self.quality_weights;
+        let proxies = self.take_ranked(amount, |proxy| {
+            Reverse(proxy.quality_score_at(&weights, now))
+        });
 
-    // XXX: need to handle clearing the env var key once that's added in
-    const FREE_LIMIT: usize = 5;
-    const PREMIUM_LIMIT: usize = 20;
+        self.throttle(proxies.len());
+        Ok(proxies)
+    }
 
-    mod functionality {
-        use iso_country::Country;
+    /// Attempts to get the specified amount of proxies that match `predicate`. Proxies that don't
+    /// match are left in the internal list for later calls. Like
+    /// [`try_get_supporting`][Fetcher::try_get_supporting], if there still aren't enough matches
+    /// after several requests then [`ApiError::AttemptsExhausted`][ApiError::AttemptsExhausted] is
+    /// returned instead of requesting forever.
+    ///
+    /// ```no_run
+    /// use lead_oxide::fetcher::Fetcher;
+    ///
+    /// let mut fetcher = Fetcher::default();
+    /// let proxies = fetcher
+    ///     .try_get_filtered(3, |proxy| proxy.supports.https && proxy.supports.cookies)
+    ///     .unwrap();
+    /// ```
+    pub fn try_get_filtered<P>(
+        &mut self,
+        amount: usize,
+        predicate: P,
+    ) -> Result<Vec<Proxy>, ApiError>
+    where
+        P: Fn(&Proxy) -> bool,
+    {
+        let opts = self.opts.clone();
+        self.fill_matching(&opts, amount, &predicate)?;
+        let proxies = self.take_where(amount, predicate);
 
-        use super::*;
-        use crate::types::{Countries, Level};
+        self.throttle(proxies.len());
+        Ok(proxies)
+    }
 
-        #[test]
-        #[serial]
-        fn api_key() {
-            let mut fetcher = Fetcher::new(Opts::builder().api_key("<key>".to_string()).build());
+    /// Like [`try_get`][Fetcher::try_get], but only returns proxies that pass
+    /// [`Proxy::is_alive`][crate::proxy::Proxy::is_alive] with the timeout from
+    /// [`with_validation_timeout`][Fetcher::with_validation_timeout]. This does real network I/O
+    /// for every candidate one after the other, so it can take a while when a lot of the proxies
+    /// are dead. Dead proxies are [`report_failure`][Fetcher::report_failure]d so they aren't
+    /// tried again for a while. If there still aren't enough live proxies after several rounds
+    /// then [`ApiError::AttemptsExhausted`][ApiError::AttemptsExhausted] is returned, and any
+    /// live proxies that were found are kept in the internal list.
+    ///
+    /// ```no_run
+    /// use lead_oxide::fetcher::Fetcher;
+    /// use std::time::Duration;
+    ///
+    /// let mut fetcher = Fetcher::default().with_validation_timeout(Duration::from_secs(2));
+    /// let proxies = fetcher.try_get_validated(3).unwrap();
+    /// ```
+    pub fn try_get_validated(&mut self, amount: usize) -> Result<Vec<Proxy>, ApiError> {
+        let mut alive = Vec::with_capacity(amount);
+        let mut attempts = 0;
+        while alive.len() < amount {
+            if attempts == self.policy.max_attempts {
+                self.restore(alive);
+                return Err(ApiError::AttemptsExhausted { attempts });
+            }
+            attempts += 1;
 
-            let single = fetcher.try_get(1).unwrap();
-            let triple = fetcher.try_get(3).unwrap();
-            let the_rest = fetcher.drain();
+            let needed = amount - alive.len();
+            if let Err(err) = self.fill(needed.saturating_sub(self.seeds.len())) {
+                self.restore(alive);
+                return Err(err);
+            }
 
-            assert_eq!(single.len(), 1);
-            assert_eq!(triple.len(), 3);
-            assert_eq!(PREMIUM_LIMIT, single.len() + triple.len() + the_rest.len());
+            for proxy in self.take_unthrottled(needed) {
+                if proxy.is_alive(self.validation_timeout) {
+                    alive.push(proxy);
+                } else {
+                    self.report_failure(&proxy);
+                }
+            }
         }
 
-        #[test]
-        #[serial]
-        fn keyless() {
-            let mut fetcher = Fetcher::default();
+        self.throttle(alive.len());
+        Ok(alive)
+    }
 
-            let single = fetcher.try_get(1).unwrap();
-            let triple = fetcher.try_get(3).unwrap();
-            let the_rest = fetcher.drain();
+    /// Attempts to get the specified amount of proxies that have all the `required` capabilities
+    /// from `filter`, ranked by how many of its `preferred` capabilities they have. Proxies that
+    /// don't meet the requirements are left in the internal list. The API doesn't allow for
+    /// filtering on all of these at once, so if there still aren't enough after several requests
+    /// then [`ApiError::AttemptsExhausted`][ApiError::AttemptsExhausted] is returned. Consider
+    /// filtering with the `Fetcher`'s `Opts` too to avoid this.
+    ///
+    /// ```no_run
+    /// use lead_oxide::{
+    ///     fetcher::Fetcher,
+    ///     proxy::{Supports, SupportsFilter},
+    /// };
+    ///
+    /// let mut fetcher = Fetcher::default();
+    /// let filter = SupportsFilter {
+    ///     required: Supports {
+    ///         cookies: true,
+    ///         ..Supports::default()
+    ///     },
+    ///     preferred: Supports {
+    ///         referer: true,
+    ///         ..Supports::default()
+    ///     },
+    /// };
+    /// let proxies = fetcher.try_get_supporting(3, &filter).unwrap();
+    /// ```
+    pub fn try_get_supporting(
+        &mut self,
+        amount: usize,
+        filter: &SupportsFilter,
+    ) -> Result<Vec<Proxy>, ApiError> {
+        let opts = self.opts.clone();
+        self.fill_matching(&opts, amount, |proxy| filter.permits(proxy))?;
 
-            assert_eq!(single.len(), 1);
-            assert_eq!(triple.len(), 3);
-            assert_eq!(FREE_LIMIT, single.len() + triple.len() + the_rest.len());
+        let (mut matching, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut self.proxies)
+            .into_iter()
+            .partition(|proxy| filter.permits(proxy));
+        self.proxies = rest;
+        matching.sort_by_key(|proxy| Reverse(filter.score(proxy)));
+        let unused = matching.split_off(amount);
+        self.restore(unused);
+
+        self.throttle(matching.len());
+        Ok(matching)
+    }
+
+    /// Attempts to get `amount` proxies split between countries proportionally to their `weights`.
+    /// The shares are rounded so that they always add up to `amount`, with any leftover proxies
+    /// going to the countries that were closest to rounding up. Each country's share is gathered
+    /// like with [`try_get_one_per`][Fetcher::try_get_one_per], so if a country's share can't be
+    /// found after several requests then
+    /// [`ApiError::CountryExhausted`][ApiError::CountryExhausted] is returned while any proxies
+    /// that were found are kept in the internal list. Weights that aren't positive are ignored, and
+    /// if none of them are positive then [`ApiError::NoPositiveWeight`][ApiError::NoPositiveWeight]
+    /// is returned. Asking for `0` proxies always returns right away without making any requests.
+    ///
+    /// ```no_run
+    /// use iso_country::Country;
+    /// use lead_oxide::fetcher::Fetcher;
+    ///
+    /// let mut fetcher = Fetcher::default();
+    /// // Roughly 70% from the US and 30% from Germany
+    /// let proxies = fetcher
+    ///     .try_get_distributed(10, &[(Country::US, 0.7), (Country::DE, 0.3)])
+    ///     .unwrap();
+    /// ```
+    pub fn try_get_distributed(
+        &mut self,
+        amount: usize,
+        weights: &[(Country, f64)],
+    ) -> Result<Vec<Proxy>, ApiError> {
+        if amount == 0 {
+            return Ok(Vec::new());
         }
 
-        #[test]
-        #[serial]
-        fn multiple_requests() {
-            // Multiple requests can be done with a single method call
-            for i in 0..=2 * FREE_LIMIT {
-                let mut fetcher = Fetcher::default();
-                let proxies = fetcher.try_get(i).unwrap();
-                assert_eq!(proxies.len(), i);
+        let shares = allocate(
+            amount,
+            &weights
+                .iter()
+                .map(|&(_, weight)| weight)
+                .collect::<Vec<_>>(),
+        )
+        .ok_or(ApiError::NoPositiveWeight)?;
+        let mut found = Vec::with_capacity(amount);
+
+        for (&(country, _), share) in weights.iter().zip(shares) {
+            if let Err(err) = self.fill_from(country, share) {
+                self.restore(found);
+                return Err(err);
             }
+
+            found.append(&mut self.take_where(share, |proxy| proxy.country == country));
         }
 
-        #[test]
+        self.throttle(found.len());
+        Ok(found)
+    }
+
+    /// Attempts to get `amount` proxies from as many different countries as possible without having
+    /// to pick the countries up front. Extra pages are requested while there are fewer countries in
+    /// the internal list than `amount`, stopping early once a page doesn't turn up any new countries
+    /// or after several requests. Proxies are then picked greedily, taking one from each country
+    /// that isn't represented yet before doubling up on any. This only draws from the internal
+    /// list, so any seeds from [`prefer_seeds`][Fetcher::prefer_seeds] aren't used.
+    ///
+    /// ```no_run
+    /// use lead_oxide::fetcher::Fetcher;
+    ///
+    /// let mut fetcher = Fetcher::default();
+    /// let proxies = fetcher.try_get_diverse(5).unwrap();
+    /// ```
+    pub fn try_get_diverse(&mut self, amount: usize) -> Result<Vec<Proxy>, ApiError> {
+        self.fill(amount)?;
+
+        let opts = self.opts.clone();
+        let mut countries = self.buffered_countries();
+        for _ in 0..self.policy.max_attempts {
+            if countries >= amount {
+                break;
+            }
+
+            let proxies = match self.request(&opts) {
+                Ok(proxies) => proxies,
+                // We already have enough proxies, just not as diverse as we would like
+                Err(ApiError::NoProxy) => break,
+                Err(err) => return Err(err),
+            };
+            self.buffer_page(proxies);
+
+            let before = countries;
+            countries = self.buffered_countries();
+            if countries == before {
+                break;
+            }
+        }
+
+        let mut diverse = Vec::with_capacity(amount);
+        while diverse.len() < amount {
+            // Each round takes at most one proxy from every country
+            let mut seen = HashSet::new();
+            let mut round =
+                self.take_where(amount - diverse.len(), |proxy| seen.insert(proxy.country));
+            diverse.append(&mut round);
+        }
+
+        self.throttle(diverse.len());
+        Ok(diverse)
+    }
+
+    // The number of distinct countries in the internal list
+    fn buffered_countries(&self) -> usize {
+        self.proxies
+            .iter()
+            .map(|proxy| proxy.country)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Returns an iterator that yields proxies one at a time using [`try_get`][Fetcher::try_get], so
+    /// the standard iterator adapters can be used to take as many as needed. The iterator ends
+    /// after yielding any error. Keep in mind that each call to `next` can block on the delay
+    /// between requests when no API key is used.
+    ///
+    /// ```no_run
+    /// use lead_oxide::fetcher::Fetcher;
+    ///
+    /// let mut fetcher = Fetcher::default();
+    /// for proxy in fetcher.iter().take(10) {
+    ///     println!("{:#?}", proxy.unwrap());
+    /// }
+    /// ```
+    pub fn iter(&mut self) -> Iter<'_> {
+        Iter {
+            fetcher: self,
+            done: false,
+        }
+    }
+
+    /// Returns an iterator that lazily yields proxies that were last checked within `max_age`,
+    /// fetching more from the API whenever the internal list runs out of fresh ones. Stale proxies
+    /// are skipped over but stay in the internal list. If no fresh proxies turn up after several
+    /// requests then [`ApiError::AttemptsExhausted`][ApiError::AttemptsExhausted] is yielded, and
+    /// the iterator ends after yielding any error.
+    ///
+    /// ```no_run
+    /// use lead_oxide::fetcher::Fetcher;
+    /// use std::time::Duration;
+    ///
+    /// let mut fetcher = Fetcher::default();
+    /// // Up to 5 proxies checked within the last 10 minutes
+    /// for proxy in fetcher.fresh_iter(Duration::from_secs(10 * 60)).take(5) {
+    ///     println!("{:#?}", proxy.unwrap());
+    /// }
+    /// ```
+    pub fn fresh_iter(&mut self, max_age: Duration) -> FreshIter<'_> {
+        FreshIter {
+            fetcher: self,
+            max_age,
+            done: false,
+        }
+    }
+
+    /// Returns up to `up_to` proxies that are immediately available from the internal list (and any
+    /// seeds) without ever requesting the API or waiting. This can return fewer proxies than asked
+    /// for, including none at all. The proxies still count towards the output rate from
+    /// [`with_output_rate`][Fetcher::with_output_rate], so any following calls to methods like
+    /// [`try_get`][Fetcher::try_get] are throttled after the burst.
+    ///
+    /// ```
+    /// use lead_oxide::fetcher::Fetcher;
+    ///
+    /// let mut fetcher = Fetcher::default();
+    /// // Nothing has been fetched yet
+    /// assert!(fetcher.try_burst(10).is_empty());
+    /// ```
+    pub fn try_burst(&mut self, up_to: usize) -> Vec<Proxy> {
+        // Prefetching would be a request, so the burst only drains what's already here
+        let proxies = self.take_without_prefetch(up_to);
+        if let Some(output_rate) = &mut self.output_rate {
+            output_rate.record(proxies.len(), Instant::now());
+        }
+
+        proxies
+    }
+
+    /// Attempts to get `amount` proxies whose sockets aren't in `in_use`, which is handy when
+    /// rotating away from proxies that are currently being used. Proxies in `in_use` are left in
+    /// the internal list. If there still aren't enough after several requests then
+    /// [`ApiError::AttemptsExhausted`][ApiError::AttemptsExhausted] is returned.
+    ///
+    /// ```no_run
+    /// use lead_oxide::fetcher::Fetcher;
+    /// use std::collections::HashSet;
+    ///
+    /// let mut fetcher = Fetcher::default();
+    /// let in_use: HashSet<_> = fetcher
+    ///     .try_get(3)
+    ///     .unwrap()
+    ///     .iter()
+    ///     .map(|proxy| proxy.socket)
+    ///     .collect();
+    /// let replacements = fetcher.try_get_excluding(3, &in_use).unwrap();
+    /// ```
+    pub fn try_get_excluding(
+        &mut self,
+        amount: usize,
+        in_use: &HashSet<SocketAddr>,
+    ) -> Result<Vec<Proxy>, ApiError> {
+        let unused = |proxy: &Proxy| !in_use.contains(&proxy.socket);
+        let opts = self.opts.clone();
+        self.fill_matching(&opts, amount, unused)?;
+
+        let proxies = self.take_where(amount, unused);
+        self.throttle(proxies.len());
+        Ok(proxies)
+    }
+
+    /// Attempts to get exactly one proxy from each of the provided `countries` returned in the same
+    /// order. Proxies already in the internal list are used when possible, otherwise the API is
+    /// requested for proxies in that specific country (ignoring the country filter from the
+    /// `Fetcher`'s `Opts`). If a country doesn't have any proxies after several requests then
+    /// [`ApiError::CountryExhausted`][ApiError::CountryExhausted] is returned with the country that
+    /// couldn't be found. Like
+    /// [`try_get`][Fetcher::try_get], any proxies gathered before an error are kept in the internal
+    /// list.
+    ///
+    /// ```no_run
+    /// use iso_country::Country;
+    /// use lead_oxide::fetcher::Fetcher;
+    ///
+    /// let mut fetcher = Fetcher::default();
+    /// let proxies = fetcher.try_get_one_per(&[Country::US, Country::DE, Country::JP]).unwrap();
+    /// ```
+    pub fn try_get_one_per(&mut self, countries: &[Country]) -> Result<Vec<Proxy>, ApiError> {
+        let mut found = Vec::with_capacity(countries.len());
+
+        for &country in countries {
+            if let Err(err) = self.fill_from(country, 1) {
+                self.restore(found);
+                return Err(err);
+            }
+
+            let pos = self
+                .proxies
+                .iter()
+                .rposition(|proxy| proxy.country == country)
+                .expect("The internal list was just filled");
+            found.push(self.proxies.remove(pos));
+        }
+
+        self.throttle(found.len());
+        Ok(found)
+    }
+
+    /// Does a single request to the API and returns the resulting page of proxies. Unlike
+    /// [`try_get`][Fetcher::try_get] this bypasses the internal list entirely, so it's meant for when
+    /// you want explicit control over each request. Running out of matching proxies returns an
+    /// empty final page instead of [`ApiError::NoProxy`][ApiError::NoProxy].
+    ///
+    /// ```no_run
+    /// use lead_oxide::fetcher::Fetcher;
+    ///
+    /// let mut fetcher = Fetcher::default();
+    /// loop {
+    ///     let page = fetcher.fetch_page().unwrap();
+    ///     println!("Proxies starting at {}: {:#?}", page.offset, page.proxies);
+    ///     if !page.has_more() {
+    ///         break;
+    ///     }
+    /// }
+    /// ```
+    pub fn fetch_page(&mut self) -> Result<Page, ApiError> {
+        let opts = self.opts.clone();
+        let proxies = match self.request(&opts) {
+            Ok(proxies) => proxies,
+            Err(ApiError::NoProxy) => Vec::new(),
+            Err(err) => return Err(err),
+        };
+        // Check for a full page before any failed proxies are removed
+        let full = proxies.len() >= self.opts.limit as usize;
+        let proxies = self.dedup_page(proxies);
+        let proxies = self.eligible(proxies, Instant::now());
+        self.throttle(proxies.len());
+
+        let offset = self.page_offset;
+        self.page_offset += proxies.len();
+        let next = if full { Some(self.page_offset) } else { None };
+
+        Ok(Page {
+            proxies,
+            offset,
+            next,
+        })
+    }
+
+    /// Fetches a single page from the API into the internal list and returns how many proxies were
+    /// added. Unlike the automatic refills done by [`try_get`][Fetcher::try_get] this always makes a
+    /// request, even if there are already proxies in the internal list, so it can be used to make sure
+    /// a fresh page is at hand before a latency-critical section. The usual delays still apply.
+    ///
+    /// ```no_run
+    /// use lead_oxide::fetcher::Fetcher;
+    ///
+    /// let mut fetcher = Fetcher::default();
+    /// let added = fetcher.warm().unwrap();
+    /// // Served straight from the internal list without waiting on the API
+    /// let proxies = fetcher.try_get(added).unwrap();
+    /// ```
+    pub fn warm(&mut self) -> Result<usize, ApiError> {
+        self.finish_prefetch();
+        let opts = self.opts.clone();
+        let proxies = self.request(&opts)?;
+
+        Ok(self.buffer_page(proxies))
+    }
+
+    // Requests the API until there are at least `amount` proxies in the internal list, bailing
+    // once a request doesn't add anything since it would just keep spinning otherwise
+    fn fill(&mut self, amount: usize) -> Result<(), ApiError> {
+        // Only wait on a prefetch when the internal list can't cover `amount` on its own
+        self.merge_prefetch(self.proxies.len() < amount);
+        let opts = self.opts.clone();
+        while self.proxies.len() < amount {
+            let proxies = self.request(&opts)?;
+            if self.buffer_page(proxies) == 0 {
+                return Err(ApiError::Exhausted);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Requests the API for proxies in `country` until there are `amount` in the internal list,
+    // saying which country it was if they can't be found
+    fn fill_from(&mut self, country: Country, amount: usize) -> Result<(), ApiError> {
+        let opts = self
+            .opts
+            .with_countries(Countries::allow().country(country));
+        self.fill_matching(&opts, amount, |proxy| proxy.country == country)
+            .map_err(|err| match err {
+                ApiError::AttemptsExhausted { attempts } => {
+                    ApiError::CountryExhausted { country, attempts }
+                }
+                err => err,
+            })
+    }
+
+    // Requests the API with `opts` until at least `amount` proxies in the internal list match
+    // `predicate`, giving up after the policy's `max_attempts` requests
+    fn fill_matching<P>(&mut self, opts: &Opts, amount: usize, predicate: P) -> Result<(), ApiError>
+    where
+        P: Fn(&Proxy) -> bool,
+    {
+        let matching = |proxies: &[Proxy]| proxies.iter().filter(|proxy| predicate(proxy)).count();
+        self.merge_prefetch(matching(&self.proxies) < amount);
+        let mut attempts = 0;
+        while matching(&self.proxies) < amount {
+            if attempts == self.policy.max_attempts {
+                return Err(ApiError::AttemptsExhausted { attempts });
+            }
+            attempts += 1;
+
+            let proxies = self.request(opts)?;
+            self.buffer_page(proxies);
+        }
+
+        Ok(())
+    }
+
+    // Takes up to `amount` of the seeds and internal list ranked together by `key`, lowest first.
+    // Ties keep their usual order so seeds still win them, and anything that isn't taken stays
+    // where it was so leftover seeds keep their priority
+    fn take_ranked<K, F>(&mut self, amount: usize, key: F) -> Vec<Proxy>
+    where
+        K: PartialOrd,
+        F: Fn(&Proxy) -> K,
+    {
+        let seeds = self.seeds.len();
+        let mut slots: Vec<_> = self.take_buffered().into_iter().map(Some).collect();
+        let keys: Vec<_> = slots.iter().flatten().map(key).collect();
+        let mut ranked: Vec<_> = (0..slots.len()).collect();
+        ranked.sort_by(|&a, &b| keys[a].partial_cmp(&keys[b]).unwrap_or(Ordering::Equal));
+
+        let taken = ranked
+            .into_iter()
+            .take(amount)
+            .map(|i| slots[i].take().expect("Each proxy is only taken once"))
+            .collect();
+        for (i, proxy) in slots.into_iter().enumerate() {
+            if let Some(proxy) = proxy {
+                if i < seeds {
+                    self.seeds.push(proxy);
+                } else {
+                    self.proxies.push(proxy);
+                }
+            }
+        }
+
+        taken
+    }
+
+    // Removes up to `amount` proxies matching `predicate` from the internal list
+    fn take_where<P>(&mut self, amount: usize, mut predicate: P) -> Vec<Proxy>
+    where
+        P: FnMut(&Proxy) -> bool,
+    {
+        let mut taken = Vec::with_capacity(amount);
+        let mut rest = Vec::with_capacity(self.proxies.len());
+        for proxy in std::mem::take(&mut self.proxies) {
+            if taken.len() < amount && predicate(&proxy) {
+                taken.push(proxy);
+            } else {
+                rest.push(proxy);
+            }
+        }
+
+        self.proxies = rest;
+        taken
+    }
+
+    /// Internal
+    ///
+    /// Does a single request to the API holding on to the raw response if asked to. The proxies
+    /// come back exactly as the API sent them without any dedup or failure filtering
+    pub(crate) fn request(&mut self, opts: &Opts) -> Result<Vec<Proxy>, ApiError> {
+        let (proxies, raw) = self.fetch_delayed(opts)?;
+        if self.keep_raw {
+            self.last_raw_response = Some(raw);
+        }
+
+        Ok(proxies)
+    }
+
+    /// Internal
+    pub(crate) fn is_premium(&self) -> bool {
+        self.opts.is_premium() && !self.force_keyless
+    }
+
+    // Does a single request to the API while coordinating delays with the `Session`
+    fn fetch_delayed(&self, opts: &Opts) -> Result<(Vec<Proxy>, String), ApiError> {
+        self.check_daily_limit()?;
+
+        if self.is_premium() {
+            // Don't need to mess with any delays if we're using an api key. (This information
+            // was based off emailing the dev. I never got an api key to test)
+            self.fetch(opts)
+        } else {
+            // If we don't have an api key then we need to coordinate delays to ensure we don't
+            // do more than one request per delay
+            let _contender = self.session.contend();
+            let mut last_fetched = match self.session.last_fetched.lock() {
+                Ok(last_fetched) => last_fetched,
+                Err(err) => {
+                    // If the lock was poisoned then play it safe and reset the timer
+                    let mut poisioned = err.into_inner();
+                    *poisioned = Some(Instant::now());
+                    poisioned
+                }
+            };
+
+            // Delay to prevent rate limiting
+            if let Some(last_fetched) = *last_fetched {
+                let delta = Instant::now().duration_since(last_fetched);
+                if delta < self.policy.delay {
+                    let wait = self.policy.delay - delta;
+                    debug!("Waiting {:?} before the next keyless request", wait);
+                    self.session.sleep(wait);
+                }
+            }
+
+            let proxies = self.fetch(opts);
+
+            // Update the request time
+            *last_fetched = Some(Instant::now());
+
+            proxies
+        }
+    }
+
+    /// Internal
+    ///
+    /// Decides what to do with the outcome of a single request to the API, so the blocking and
+    /// `async` fetchers only differ in how they do the request and the waiting. `retries` counts
+    /// the `Retry-After`s that were waited out and `transient_retries` the transient errors
+    pub(crate) fn classify(
+        &self,
+        naive_resp: Result<NaiveResponse, ApiError>,
+        retries: &mut usize,
+        transient_retries: &mut usize,
+    ) -> Step {
+        let naive_resp = match naive_resp {
+            Ok(naive_resp) => naive_resp,
+            Err(err) => return self.backoff(err, transient_retries),
+        };
+
+        // The API told us exactly how long to wait so wait it out and try again. Keyless fetches
+        // hold the session's lock while waiting so this delays the whole session
+        if let Some(retry_after) = naive_resp.rate_limited_for() {
+            if *retries < self.policy.max_retry_after {
+                *retries += 1;
+                debug!("Rate limited, retrying after {:?}", retry_after);
+                return Step::Retry(retry_after);
+            }
+        }
+
+        if !naive_resp.ok() {
+            return self.backoff(ApiError::from(naive_resp), transient_retries);
+        }
+        match proxies_from_json(&naive_resp.text) {
+            Ok(proxies) => Step::Done(Ok((proxies, naive_resp.text))),
+            Err(source) => {
+                self.backoff(ApiError::unparsable(naive_resp, source), transient_retries)
+            }
+        }
+    }
+
+    // Retries `err` if it's transient and the policy allows for another retry. The wait doubles
+    // with each retry and keyless requests still wait out the usual delay
+    fn backoff(&self, err: ApiError, retries: &mut usize) -> Step {
+        if !err.is_transient() || *retries >= self.policy.max_retries {
+            return Step::Done(Err(err));
+        }
+
+        let factor = 1u32.checked_shl(*retries as u32).unwrap_or(u32::MAX);
+        let mut wait = self.policy.retry_backoff.saturating_mul(factor);
+        if !self.is_premium() {
+            wait = wait.max(self.policy.delay);
+        }
+        *retries += 1;
+        debug!("Retrying after {:?} from: {}", wait, err);
+
+        Step::Retry(wait)
+    }
+
+    // Takes up to `amount` proxies, favoring seeds over the internal list, while respecting the
+    // output rate
+    fn take(&mut self, amount: usize) -> Vec<Proxy> {
+        self.throttle(amount.min(self.seeds.len() + self.proxies.len()));
+        self.take_unthrottled(amount)
+    }
+
+    fn take_unthrottled(&mut self, amount: usize) -> Vec<Proxy> {
+        let proxies = self.take_without_prefetch(amount);
+        self.start_prefetch();
+        proxies
+    }
+
+    /// Internal
+    ///
+    /// Takes up to `amount` proxies, favoring seeds over the internal list, without ever requesting
+    pub(crate) fn take_without_prefetch(&mut self, amount: usize) -> Vec<Proxy> {
+        let from_seeds = amount.min(self.seeds.len());
+        let from_list = (amount - from_seeds).min(self.proxies.len());
+
+        let mut proxies: Vec<_> = self.seeds.drain(..from_seeds).collect();
+        proxies.append(&mut self.proxies.split_off(self.proxies.len() - from_list));
+        proxies
+    }
+
+    // Starts fetching a page in the background if the buffer dropped below the prefetch threshold
+    fn start_prefetch(&mut self) {
+        let buffered = self.seeds.len() + self.proxies.len();
+        match &self.prefetch {
+            Some(Prefetch {
+                threshold,
+                pending: None,
+            }) if buffered < *threshold => {}
+            _ => return,
+        }
+
+        // A bare `Fetcher` with just what's needed to do the request on its own thread. Failures
+        // and the internal list are filtered against once the page is merged back in
+        let mut background = Self {
+            policy: self.policy.clone(),
+            uri: self.uri.clone(),
+            intra_page_dedup: self.intra_page_dedup,
+            force_keyless: self.force_keyless,
+            ..Self::with_session(self.opts.clone(), self.session.clone())
+        };
+        let handle = thread::spawn(move || {
+            let opts = background.opts.clone();
+            background
+                .request(&opts)
+                .map(|proxies| background.dedup_page(proxies))
+                .unwrap_or_default()
+        });
+
+        if let Some(prefetch) = &mut self.prefetch {
+            prefetch.pending = Some(handle);
+        }
+    }
+
+    // Waits on any in-progress prefetch and adds what it found to the internal list
+    fn finish_prefetch(&mut self) {
+        self.merge_prefetch(true);
+    }
+
+    // Adds what an in-progress prefetch found to the internal list, only blocking on it if `wait`
+    // is set. Otherwise a prefetch that's still going is left alone
+    fn merge_prefetch(&mut self, wait: bool) {
+        let pending = self
+            .prefetch
+            .as_mut()
+            .and_then(|prefetch| match &prefetch.pending {
+                Some(pending) if wait || pending.is_finished() => prefetch.pending.take(),
+                _ => None,
+            });
+        if let Some(pending) = pending {
+            // A panicked prefetch is treated the same as one that errored
+            let proxies = pending.join().unwrap_or_default();
+            let proxies = self.eligible(proxies, Instant::now());
+            let mut proxies = self.dedup_buffered(proxies);
+            self.proxies.append(&mut proxies);
+        }
+    }
+
+    // Blocks until serving `amount` proxies wouldn't exceed the output rate
+    fn throttle(&mut self, amount: usize) {
+        if let Some(output_rate) = &mut self.output_rate {
+            self.session
+                .sleep(output_rate.reserve(amount, Instant::now()));
+        }
+    }
+
+    /// Internal
+    pub(crate) fn check_daily_limit(&self) -> Result<(), ApiError> {
+        match self.session.daily_limit() {
+            Some(limit) if self.session.requests_today() >= limit => {
+                Err(ApiError::DailyLimitReached { limit })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn request_builder(&self, opts: &Opts) -> Result<ureq::Request, ApiError> {
+        let params = opts.to_query_string()?;
+        let mut request = self.session.get(&self.uri);
+        request.query_str(&params);
+        if let Some(request_timeout) = self.policy.request_timeout {
+            request.timeout(request_timeout);
+        }
+
+        Ok(request.build())
+    }
+
+    // Returns the parsed proxies along with the raw response they were parsed from
+    fn fetch(&self, opts: &Opts) -> Result<(Vec<Proxy>, String), ApiError> {
+        let countries = opts.allowed_countries();
+        let mut retries = 0;
+        let mut transient_retries = 0;
+
+        loop {
+            debug!("Requesting {}", self.logged_uri(opts));
+            self.session.record_request();
+            let start = Instant::now();
+
+            // Responses are mocked out when testing
+            #[cfg(not(test))]
+            let naive_resp = {
+                let resp = self.request_builder(opts)?.call();
+                NaiveResponse::from_response(resp, self.policy.max_response_bytes)
+            };
+            #[cfg(test)]
+            let naive_resp = {
+                let _ = self.request_builder(opts)?;
+                let nth = self.session.requests_today() - 1;
+                Ok(crate::mock::response(self.opts.limit as usize, nth))
+            };
+
+            self.session.record_latency(&countries, start.elapsed());
+            match self.classify(naive_resp, &mut retries, &mut transient_retries) {
+                Step::Retry(wait) => self.session.sleep(wait),
+                Step::Done(result) => {
+                    if let Ok((proxies, _)) = &result {
+                        debug!(
+                            "Got {} proxies from {}",
+                            proxies.len(),
+                            self.logged_uri(opts)
+                        );
+                    }
+                    return result;
+                }
+            }
+        }
+    }
+
+    /// The number of proxies that can be returned without making any requests. This includes any
+    /// seeds from [`prefer_seeds`][Fetcher::prefer_seeds] along with the internal list.
+    ///
+    /// ```
+    /// use lead_oxide::fetcher::Fetcher;
+    ///
+    /// let fetcher = Fetcher::default();
+    /// // Nothing has been fetched yet
+    /// assert_eq!(fetcher.available(), 0);
+    /// ```
+    pub fn available(&self) -> usize {
+        self.seeds.len() + self.proxies.len()
+    }
+
+    /// Saves the internal list to a JSON file at `path` so that it can be restored with
+    /// [`load_cache`][Fetcher::load_cache] later on, like on the next run of a program. This
+    /// avoids waiting on the API for proxies that were already fetched. Seeds aren't included.
+    ///
+    /// ```no_run
+    /// use lead_oxide::fetcher::Fetcher;
+    /// use std::time::Duration;
+    ///
+    /// let mut fetcher = Fetcher::default();
+    /// fetcher.load_cache("proxies.json", Duration::from_secs(30 * 60)).ok();
+    /// let proxies = fetcher.try_get(3).unwrap();
+    /// fetcher.save_cache("proxies.json").unwrap();
+    /// ```
+    pub fn save_cache(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(writer, &self.proxies)?;
+
+        Ok(())
+    }
+
+    /// Adds the proxies saved with [`save_cache`][Fetcher::save_cache] at `path` to the internal
+    /// list and returns how many were added. Proxies that weren't checked within `max_age` are
+    /// dropped since they're unlikely to still work, along with any that recently failed.
+    pub fn load_cache(&mut self, path: impl AsRef<Path>, max_age: Duration) -> io::Result<usize> {
+        let reader = BufReader::new(File::open(path)?);
+        let cached: Vec<Proxy> = serde_json::from_reader(reader)?;
+        let cached = cached
+            .into_iter()
+            .filter(|proxy| checked_within(proxy, max_age))
+            .collect();
+        let cached = self.eligible(cached, Instant::now());
+        let mut cached = self.dedup_buffered(cached);
+        let added = cached.len();
+        self.proxies.append(&mut cached);
+
+        Ok(added)
+    }
+
+    /// The proxies currently held in the internal list, without removing them like
+    /// [`drain`][Fetcher::drain] does. Seeds aren't included.
+    ///
+    /// ```
+    /// use lead_oxide::fetcher::Fetcher;
+    ///
+    /// let fetcher = Fetcher::default();
+    /// // Nothing has been fetched yet
+    /// assert!(fetcher.buffered().is_empty());
+    /// ```
+    pub fn buffered(&self) -> &[Proxy] {
+        &self.proxies
+    }
+
+    /// Returns an iterator over the proxies in the internal list that are located in `country`
+    /// without removing them from the list.
+    ///
+    /// ```
+    /// use iso_country::Country;
+    /// use lead_oxide::fetcher::Fetcher;
+    ///
+    /// let fetcher = Fetcher::default();
+    /// // Nothing has been fetched yet
+    /// assert_eq!(fetcher.buffered_in(Country::US).count(), 0);
+    /// ```
+    pub fn buffered_in(&self, country: Country) -> impl Iterator<Item = &Proxy> {
+        self.proxies
+            .iter()
+            .filter(move |proxy| proxy.country == country)
+    }
+
+    /// The body of the most recent successful response from the API if
+    /// [`keep_raw`][Fetcher::keep_raw] is enabled.
+    ///
+    /// ```no_run
+    /// use lead_oxide::fetcher::Fetcher;
+    ///
+    /// let mut fetcher = Fetcher::default().keep_raw(true);
+    /// fetcher.try_get(1).unwrap();
+    /// let raw: serde_json::Value = serde_json::from_str(fetcher.last_raw_response().unwrap()).unwrap();
+    /// println!("The API returned {} proxies", raw["count"]);
+    /// ```
+    pub fn last_raw_response(&self) -> Option<&str> {
+        self.last_raw_response.as_deref()
+    }
+
+    /// Takes a snapshot of the `Fetcher`'s current configuration and state.
+    ///
+    /// ```
+    /// use lead_oxide::fetcher::Fetcher;
+    ///
+    /// let fetcher = Fetcher::default();
+    /// let snapshot = fetcher.snapshot();
+    /// assert_eq!(snapshot.buffered, 0);
+    /// println!("{}", serde_json::to_string_pretty(&snapshot).unwrap());
+    /// ```
+    pub fn snapshot(&self) -> FetcherSnapshot {
+        let time_until_ready = if self.is_premium() {
+            Duration::from_secs(0)
+        } else {
+            self.session.time_until_ready(self.policy.delay)
+        };
+
+        FetcherSnapshot {
+            opts: self.opts.redacted(),
+            buffered: self.available(),
+            time_until_ready,
+            requests_today: self.session.requests_today(),
+        }
+    }
+
+    /// Internal
+    pub(crate) fn take_buffered(&mut self) -> Vec<Proxy> {
+        let mut proxies = std::mem::take(&mut self.seeds);
+        proxies.append(&mut self.proxies);
+        proxies
+    }
+
+    /// Internal
+    pub(crate) fn restore(&mut self, mut proxies: Vec<Proxy>) {
+        self.proxies.append(&mut proxies);
+    }
+
+    /// Internal
+    #[cfg(feature = "async")]
+    pub(crate) fn opts(&self) -> &Opts {
+        &self.opts
+    }
+
+    /// Internal
+    ///
+    /// The url a request with `opts` goes to with any api key redacted, since that's all that
+    /// should ever end up in the logs. This is only built when it's actually logged
+    pub(crate) fn logged_uri(&self, opts: &Opts) -> String {
+        // `Opts` that can't be represented already failed the request itself
+        let query = opts.redacted().to_query_string().unwrap_or_default();
+        format!("{}{}", self.uri, query)
+    }
+
+    /// Internal
+    #[cfg(all(feature = "async", not(test)))]
+    pub(crate) fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Internal
+    #[cfg(feature = "async")]
+    pub(crate) fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Consumes the `Fetcher` returning any proxies still left in the internal list (including any
+    /// unused seeds).
+    pub fn drain(mut self) -> Vec<Proxy> {
+        self.take_buffered()
+    }
+}
+
+/// An iterator over freshly checked proxies.
+///
+/// Returned from [`Fetcher::fresh_iter`][Fetcher::fresh_iter].
+#[derive(Debug)]
+pub struct FreshIter<'a> {
+    fetcher: &'a mut Fetcher,
+    max_age: Duration,
+    done: bool,
+}
+
+impl Iterator for FreshIter<'_> {
+    type Item = Result<Proxy, ApiError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let max_age = self.max_age;
+        let is_fresh = |proxy: &Proxy| checked_within(proxy, max_age);
+
+        let opts = self.fetcher.opts.clone();
+        match self.fetcher.fill_matching(&opts, 1, is_fresh) {
+            Ok(()) => {
+                let proxy = self.fetcher.take_where(1, is_fresh).pop();
+                self.fetcher.throttle(1);
+                proxy.map(Ok)
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// An iterator over proxies from a [`Fetcher`][Fetcher].
+///
+/// Returned from [`Fetcher::iter`][Fetcher::iter].
+#[derive(Debug)]
+pub struct Iter<'a> {
+    fetcher: &'a mut Fetcher,
+    done: bool,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = Result<Proxy, ApiError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.fetcher.try_get(1) {
+            Ok(mut proxies) => proxies.pop().map(Ok),
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+// If `proxy` was last checked within `max_age`. Anything checked in the future is as fresh as it
+// gets
+pub(crate) fn checked_within(proxy: &Proxy, max_age: Duration) -> bool {
+    Utc::now()
+        .naive_utc()
+        .signed_duration_since(proxy.last_checked)
+        .to_std()
+        .map_or(true, |age| age <= max_age)
+}
+
+// Orders `proxies` so that the same country shows up back to back as little as possible by always
+// picking from the country with the most proxies left that wasn't just picked
+fn interleave(proxies: Vec<Proxy>) -> Vec<Proxy> {
+    let mut by_country: Vec<(Country, VecDeque<Proxy>)> = Vec::new();
+    let len = proxies.len();
+    for proxy in proxies {
+        match by_country
+            .iter_mut()
+            .find(|(country, _)| *country == proxy.country)
+        {
+            Some((_, group)) => group.push_back(proxy),
+            None => by_country.push((proxy.country, vec![proxy].into())),
+        }
+    }
+
+    let mut interleaved: Vec<Proxy> = Vec::with_capacity(len);
+    while interleaved.len() < len {
+        let last = interleaved.last().map(|proxy| proxy.country);
+        let most_left = |skip_last: bool| {
+            by_country
+                .iter()
+                .enumerate()
+                .filter(|(_, (country, group))| {
+                    !group.is_empty() && (!skip_last || Some(*country) != last)
+                })
+                // Ties go to the country that showed up first
+                .max_by_key(|(i, (_, group))| (group.len(), Reverse(*i)))
+                .map(|(i, _)| i)
+        };
+
+        // Only repeat the last country when it's all that's left
+        let i = most_left(true)
+            .or_else(|| most_left(false))
+            .expect("There are proxies left");
+        let proxy = by_country[i].1.pop_front().expect("Groups are non-empty");
+        interleaved.push(proxy);
+    }
+
+    interleaved
+}
+
+// Splits `amount` proportionally to `weights` with the largest remainder method so that the
+// shares always add up to `amount`. Returns `None` if there's no positive weight to split between
+fn allocate(amount: usize, weights: &[f64]) -> Option<Vec<usize>> {
+    let weights: Vec<_> = weights
+        .iter()
+        .map(|&weight| {
+            if weight > 0.0 && weight.is_finite() {
+                weight
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 {
+        return None;
+    }
+
+    let exact: Vec<_> = weights
+        .iter()
+        .map(|weight| amount as f64 * weight / total)
+        .collect();
+    let mut shares: Vec<_> = exact.iter().map(|share| share.floor() as usize).collect();
+
+    // Hand out whatever is left to the largest remainders, favoring earlier entries on ties
+    let mut by_remainder: Vec<_> = (0..exact.len()).filter(|&i| weights[i] > 0.0).collect();
+    by_remainder.sort_by(|&a, &b| {
+        let remainder = |i: usize| exact[i] - exact[i].floor();
+        remainder(b)
+            .partial_cmp(&remainder(a))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let leftover = amount - shares.iter().sum::<usize>();
+    for &i in by_remainder.iter().cycle().take(leftover) {
+        shares[i] += 1;
+    }
+
+    Some(shares)
+}
+
+impl Default for Fetcher {
+    fn default() -> Self {
+        Self::new(Opts::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serial_test::serial;
+
+    use super::*;
+
+    const FREE_LIMIT: usize = crate::opts::FREE_LIMIT as usize;
+    const PREMIUM_LIMIT: usize = crate::opts::PREMIUM_LIMIT as usize;
+
+    // Creates a default `Fetcher` with `proxies` already in the internal list
+    fn seeded(proxies: Vec<Proxy>) -> Fetcher {
+        let mut fetcher = Fetcher::new(Opts::default());
+        fetcher.proxies = proxies;
+        fetcher
+    }
+
+    mod functionality {
+        use std::net::TcpListener;
+
+        use iso_country::Country;
+
+        use super::*;
+        use crate::{
+            mock,
+            proxy::Supports,
+            types::{Countries, Level, Protocol},
+        };
+
+        #[test]
+        #[serial]
+        fn api_key() {
+            let mut fetcher = Fetcher::new(Opts::builder().api_key("<key>".to_string()).build());
+
+            let single = fetcher.try_get(1).unwrap();
+            let triple = fetcher.try_get(3).unwrap();
+            let the_rest = fetcher.drain();
+
+            assert_eq!(single.len(), 1);
+            assert_eq!(triple.len(), 3);
+            assert_eq!(PREMIUM_LIMIT, single.len() + triple.len() + the_rest.len());
+        }
+
+        #[test]
+        #[serial]
+        fn keyless() {
+            let mut fetcher = Fetcher::default();
+
+            let single = fetcher.try_get(1).unwrap();
+            let triple = fetcher.try_get(3).unwrap();
+            let the_rest = fetcher.drain();
+
+            assert_eq!(single.len(), 1);
+            assert_eq!(triple.len(), 3);
+            assert_eq!(FREE_LIMIT, single.len() + triple.len() + the_rest.len());
+        }
+
+        #[test]
+        #[serial]
+        fn multiple_requests() {
+            // Multiple requests can be done with a single method call
+            for i in 0..=2 * FREE_LIMIT {
+                let mut fetcher = Fetcher::default();
+                let proxies = fetcher.try_get(i).unwrap();
+                assert_eq!(proxies.len(), i);
+            }
+        }
+
+        #[test]
         #[serial]
         fn multiple_fetchers() {
             // Each fetcher should be independent
@@ -246,25 +1874,1178 @@ mod tests {
             let mut custom = Fetcher::new(
                 Opts::builder()
                     .level(Level::Elite)
-                    .cookies(true)
-                    .countries(Countries::allow().country(Country::CA))
-                    .build(),
+                    .cookies(true)
+                    .countries(Countries::allow().country(Country::CA))
+                    .build(),
+            );
+
+            let single = default.try_get(1).unwrap();
+            let double = premium.try_get(2).unwrap();
+            let triple = custom.try_get(3).unwrap();
+            assert_eq!(single.len(), 1);
+            assert_eq!(double.len(), 2);
+            assert_eq!(triple.len(), 3);
+            assert_eq!(default.drain().len(), FREE_LIMIT - single.len());
+            assert_eq!(premium.drain().len(), PREMIUM_LIMIT - double.len());
+            assert_eq!(custom.drain().len(), FREE_LIMIT - triple.len());
+        }
+
+        #[test]
+        fn buffered_in() {
+            let proxy_in = |country| Proxy::builder().country(country).build();
+
+            let fetcher = seeded(vec![
+                proxy_in(Country::US),
+                proxy_in(Country::CA),
+                proxy_in(Country::US),
+                proxy_in(Country::DE),
+            ]);
+
+            assert_eq!(fetcher.buffered_in(Country::US).count(), 2);
+            assert!(fetcher
+                .buffered_in(Country::US)
+                .all(|proxy| proxy.country == Country::US));
+            assert_eq!(fetcher.buffered_in(Country::CA).count(), 1);
+            assert_eq!(fetcher.buffered_in(Country::JP).count(), 0);
+
+            // The internal list is left untouched
+            assert_eq!(fetcher.drain().len(), 4);
+        }
+
+        #[test]
+        fn output_rate() {
+            let per = Duration::from_millis(200);
+            let mut fetcher = Session::new()
+                .fetcher(Opts::default())
+                .with_output_rate(NonZeroU32::new(2).unwrap(), per);
+
+            // Can get up to the rate at once
+            let start = Instant::now();
+            assert_eq!(fetcher.try_get(2).unwrap().len(), 2);
+            assert!(start.elapsed() < per);
+
+            // But then has to wait (even though there are proxies in the internal list)
+            assert_eq!(fetcher.try_get(1).unwrap().len(), 1);
+            assert!(start.elapsed() >= per);
+        }
+
+        #[test]
+        fn retry_after() {
+            let retry_after = Duration::from_millis(200);
+            let mut fetcher = Session::new().fetcher(Opts::default());
+
+            // The first request gets rate limited, but the retry goes through
+            mock::push_retry_after(retry_after);
+            let start = Instant::now();
+            assert_eq!(fetcher.try_get(1).unwrap().len(), 1);
+            assert!(start.elapsed() >= retry_after);
+            assert_eq!(fetcher.session.requests_today(), 2);
+
+            // Eventually it gives up with the rate limit
+            for _ in 0..=constants::MAX_RETRY_AFTER {
+                mock::push_retry_after(Duration::from_millis(10));
+            }
+            let mut fetcher = Session::new().fetcher(Opts::default());
+            assert!(matches!(fetcher.try_get(1), Err(ApiError::RateLimit)));
+
+            // which is the same as a 429 that never said how long to wait
+            mock::push_response(429, "Too Many Requests");
+            assert!(matches!(fetcher.try_get(1), Err(ApiError::RateLimit)));
+        }
+
+        #[test]
+        fn excluding() {
+            let mut fetcher = Session::new().fetcher(Opts::default());
+            let sockets = |pages: std::ops::Range<usize>, len| -> HashSet<_> {
+                pages
+                    .flat_map(|nth| mock::nth_page(nth, len))
+                    .map(|proxy| proxy.socket)
+                    .collect()
+            };
+            let in_use = sockets(0..3, 4);
+
+            // Only one proxy from each of the first pages isn't in use
+            let proxies = fetcher.try_get_excluding(3, &in_use).unwrap();
+            assert_eq!(proxies.len(), 3);
+            assert!(proxies.iter().all(|proxy| !in_use.contains(&proxy.socket)));
+            assert_eq!(fetcher.session.requests_today(), 3);
+            // and the ones in use are left over
+            assert_eq!(fetcher.proxies.len(), 12);
+
+            // Excluding everything can't be satisfied
+            let in_use = sockets(0..3 + constants::MAX_ATTEMPTS, FREE_LIMIT);
+            assert!(matches!(
+                fetcher.try_get_excluding(1, &in_use),
+                Err(ApiError::AttemptsExhausted { .. })
+            ));
+        }
+
+        #[test]
+        fn interleave() {
+            let countries = |proxies: &[Proxy]| -> Vec<_> {
+                proxies.iter().map(|proxy| proxy.country).collect()
+            };
+            let (us, ca, de) = (Country::US, Country::CA, Country::DE);
+            let in_countries = |countries: &[Country]| -> Vec<_> {
+                countries.iter().copied().map(mock::proxy_in).collect()
+            };
+
+            assert_eq!(
+                countries(&super::interleave(in_countries(&[us, us, us, ca, ca, de]))),
+                [us, ca, us, ca, us, de]
+            );
+            // The largest group is spread out across the whole result
+            assert_eq!(
+                countries(&super::interleave(in_countries(&[ca, us, us, us, us]))),
+                [us, ca, us, us, us]
+            );
+            assert!(super::interleave(Vec::new()).is_empty());
+
+            // And `try_get` uses it when asked
+            let mut fetcher = Session::new()
+                .fetcher(Opts::default())
+                .with_result_interleave(true);
+            let page: Vec<_> = mock::default_page(6)
+                .into_iter()
+                .zip(&[us, us, us, de, de, de])
+                .map(|(proxy, &country)| Proxy { country, ..proxy })
+                .collect();
+            mock::push_proxies(&page);
+            assert_eq!(
+                countries(&fetcher.try_get(6).unwrap()),
+                [us, de, us, de, us, de]
+            );
+        }
+
+        #[test]
+        fn one_per_country() {
+            let mut fetcher = Session::new().fetcher(Opts::default());
+
+            // US is found right away, DE takes a couple of requests, and CA was already fetched
+            let elsewhere = |country, port| Proxy {
+                socket: SocketAddr::from(([1, 2, 3, 4], port)),
+                ..mock::proxy_in(country)
+            };
+            mock::push_proxies(&mock::page_in(Country::US, 2));
+            mock::push_proxies(&[elsewhere(Country::CA, 1)]);
+            mock::push_proxies(&[elsewhere(Country::DE, 2)]);
+            let proxies = fetcher
+                .try_get_one_per(&[Country::US, Country::DE, Country::CA])
+                .unwrap();
+
+            let countries: Vec<_> = proxies.iter().map(|proxy| proxy.country).collect();
+            assert_eq!(countries, [Country::US, Country::DE, Country::CA]);
+            assert_eq!(fetcher.session.requests_today(), 3);
+            // The extra US proxy is still around
+            assert_eq!(fetcher.buffered_in(Country::US).count(), 1);
+
+            // The mocked API only ever returns CA proxies without anything queued
+            assert!(matches!(
+                fetcher.try_get_one_per(&[Country::US, Country::JP]),
+                Err(ApiError::CountryExhausted {
+                    country: Country::JP,
+                    attempts: constants::MAX_ATTEMPTS,
+                })
+            ));
+            // and the US proxy that was found is kept in the internal list
+            assert_eq!(fetcher.buffered_in(Country::US).count(), 1);
+        }
+
+        #[test]
+        fn available() {
+            let session = Session::new();
+            let mut fetcher = session.fetcher(Opts::default());
+            assert_eq!(fetcher.available(), 0);
+
+            fetcher.try_get(2).unwrap();
+            assert_eq!(fetcher.available(), FREE_LIMIT - 2);
+            fetcher.prefer_seeds(vec![mock::proxy_in(Country::JP)]);
+            assert_eq!(fetcher.available(), FREE_LIMIT - 1);
+
+            // Everything available is served without another request
+            fetcher.try_get(FREE_LIMIT - 1).unwrap();
+            assert_eq!(fetcher.available(), 0);
+            assert_eq!(session.requests_today(), 1);
+        }
+
+        #[test]
+        fn buffered() {
+            let mut fetcher = Session::new().fetcher(Opts::default());
+            fetcher.try_get(2).unwrap();
+            assert_eq!(
+                fetcher.buffered(),
+                &mock::default_page(FREE_LIMIT)[..FREE_LIMIT - 2]
+            );
+
+            // Peeking doesn't take anything out
+            assert_eq!(fetcher.buffered().len(), fetcher.available());
+            assert_eq!(
+                fetcher.try_get(FREE_LIMIT - 2).unwrap().len(),
+                FREE_LIMIT - 2
+            );
+            assert!(fetcher.buffered().is_empty());
+        }
+
+        #[test]
+        fn cache() {
+            let path =
+                std::env::temp_dir().join(format!("lead-oxide-cache-{}.json", std::process::id()));
+            let now = Utc::now().naive_utc();
+            let fresh = |port| Proxy {
+                socket: SocketAddr::from(([1, 2, 3, 4], port)),
+                last_checked: now,
+                ..mock::default_proxy()
+            };
+
+            let mut fetcher = Session::new().fetcher(Opts::default());
+            fetcher.proxies = vec![fresh(1), mock::default_proxy(), fresh(2)];
+            fetcher.save_cache(&path).unwrap();
+
+            // Restoring drops the stale proxy without any requests
+            let session = Session::new();
+            let mut restored = session.fetcher(Opts::default());
+            let added = restored.load_cache(&path, Duration::from_secs(60)).unwrap();
+            assert_eq!(added, 2);
+            assert_eq!(restored.try_get(2).unwrap(), [fresh(1), fresh(2)]);
+            assert_eq!(session.requests_today(), 0);
+
+            // A missing file is just an error
+            std::fs::remove_file(&path).unwrap();
+            assert!(restored.load_cache(&path, Duration::from_secs(60)).is_err());
+        }
+
+        #[test]
+        fn snapshot() {
+            let session = Session::new();
+
+            let mut keyless = session.fetcher(Opts::default());
+            let snapshot = keyless.snapshot();
+            assert_eq!(snapshot.buffered, 0);
+            assert_eq!(snapshot.time_until_ready, Duration::from_secs(0));
+            assert_eq!(snapshot.requests_today, 0);
+
+            keyless.try_get(1).unwrap();
+            let snapshot = keyless.snapshot();
+            assert_eq!(snapshot.buffered, FREE_LIMIT - 1);
+            assert!(snapshot.time_until_ready > Duration::from_secs(0));
+            assert!(snapshot.time_until_ready <= constants::DELAY);
+            assert_eq!(snapshot.requests_today, 1);
+
+            // Premium fetchers are never delayed and the key doesn't show up
+            let premium = session.fetcher(Opts::builder().api_key("<key>".to_string()).build());
+            let snapshot = premium.snapshot();
+            assert_eq!(snapshot.time_until_ready, Duration::from_secs(0));
+            let json = serde_json::to_value(&snapshot).unwrap();
+            assert_eq!(json["opts"]["api"], "<redacted>");
+            assert_eq!(json["buffered"], 0);
+        }
+
+        #[test]
+        fn error_kinds() {
+            let mut fetcher = Session::new().fetcher(Opts::default());
+            let mut error_for = |status, text: &str| {
+                mock::push_response(status, text);
+                fetcher.fetch_page().unwrap_err()
+            };
+
+            assert!(matches!(
+                error_for(200, "{\"unexpected\": true}"),
+                ApiError::Parse { .. }
+            ));
+            // Including a single malformed proxy
+            let bad_speed = serde_json::json!({ "data": [{
+                "ipPort": "1.2.3.4:80",
+                "country": "US",
+                "last_checked": "2020-12-13 20:06:41",
+                "proxy_level": "elite",
+                "type": "http",
+                "speed": "fast",
+                "support": {},
+            }] });
+            assert!(matches!(
+                error_for(200, &bad_speed.to_string()),
+                ApiError::Parse { .. }
+            ));
+            // The body is kept to see what the API actually sent
+            assert!(matches!(
+                error_for(200, "<html>Service moved</html>"),
+                ApiError::Parse { body, .. } if body == "<html>Service moved</html>"
+            ));
+            assert!(matches!(
+                error_for(
+                    200,
+                    "Invalid API. Get your API to make unlimited requests at \
+                     http://pubproxy.com/#premium"
+                ),
+                ApiError::ApiKey
+            ));
+            assert!(matches!(
+                error_for(404, "Not Found"),
+                ApiError::Client { status: 404, .. }
+            ));
+            assert!(matches!(
+                error_for(502, "Bad Gateway"),
+                ApiError::Server { status: 502, .. }
+            ));
+        }
+
+        #[test]
+        fn pages() {
+            let mut fetcher = Session::new().fetcher(Opts::default());
+
+            // Two full pages, a partial page, and then nothing at all
+            mock::push_proxies(&mock::default_page(FREE_LIMIT));
+            mock::push_proxies(&mock::default_page(FREE_LIMIT));
+            mock::push_proxies(&mock::default_page(2));
+            mock::push_response(200, "No proxy");
+
+            let mut offsets = Vec::new();
+            loop {
+                let page = fetcher.fetch_page().unwrap();
+                offsets.push((page.offset, page.proxies.len(), page.next));
+                if !page.has_more() {
+                    break;
+                }
+            }
+            assert_eq!(offsets, [(0, 5, Some(5)), (5, 5, Some(10)), (10, 2, None)]);
+
+            // Running out entirely is also the last page
+            let page = fetcher.fetch_page().unwrap();
+            assert!(page.proxies.is_empty());
+            assert_eq!(page.offset, 12);
+            assert!(!page.has_more());
+
+            // And the internal list is never touched
+            assert!(fetcher.drain().is_empty());
+        }
+
+        #[test]
+        fn preferring() {
+            let mut fetcher = Session::new().fetcher(Opts::default());
+            let page = mock::default_page(4);
+            let elite = Proxy {
+                level: Level::Elite,
+                ..page[1].clone()
+            };
+            let elite_us = Proxy {
+                country: Country::US,
+                level: Level::Elite,
+                ..page[3].clone()
+            };
+
+            mock::push_proxies(&[
+                page[0].clone(),
+                elite.clone(),
+                page[2].clone(),
+                elite_us.clone(),
+            ]);
+            let prefer = Opts::builder()
+                .level(Level::Elite)
+                .countries(Countries::allow().country(Country::US))
+                .build();
+            let proxies = fetcher.try_get_preferring(3, &prefer).unwrap();
+            assert_eq!(proxies, [elite_us.clone(), elite, page[0].clone()]);
+
+            // Nothing matching still returns something
+            let prefer = Opts::builder().protocol(Protocol::Socks5).build();
+            assert_eq!(fetcher.try_get_preferring(1, &prefer).unwrap().len(), 1);
+
+            // Seeds that aren't picked stay seeds instead of joining the internal list
+            let seed = Proxy {
+                socket: SocketAddr::from(([1, 2, 3, 4], 1)),
+                ..mock::default_proxy()
+            };
+            fetcher.prefer_seeds(vec![seed.clone(), elite_us.clone()]);
+            let prefer = Opts::builder().level(Level::Elite).build();
+            assert_eq!(fetcher.try_get_preferring(1, &prefer).unwrap(), [elite_us]);
+            assert_eq!(fetcher.seeds, std::slice::from_ref(&seed));
+            assert!(!fetcher.buffered().contains(&seed));
+            assert_eq!(fetcher.try_get(1).unwrap(), [seed]);
+        }
+
+        #[test]
+        fn by_level() {
+            let mut fetcher = Session::new().fetcher(Opts::default());
+            let elite = |proxy: &Proxy| Proxy {
+                level: Level::Elite,
+                ..proxy.clone()
+            };
+            let page = mock::default_page(5);
+
+            mock::push_proxies(&[
+                elite(&page[0]),
+                page[1].clone(),
+                elite(&page[2]),
+                page[3].clone(),
+                elite(&page[4]),
+            ]);
+            let by_level = fetcher.try_get_by_level(4).unwrap();
+            assert_eq!(by_level.values().map(Vec::len).sum::<usize>(), 4);
+            for (level, proxies) in &by_level {
+                assert!(proxies.iter().all(|proxy| proxy.level == *level));
+            }
+            assert!(!by_level[&Level::Elite].is_empty());
+            assert!(!by_level[&Level::Anonymous].is_empty());
+
+            // Levels without any proxies are left out
+            let by_level = fetcher.try_get_by_level(1).unwrap();
+            assert_eq!(by_level.len(), 1);
+            assert_eq!(by_level.values().next().unwrap().len(), 1);
+            assert!(fetcher.try_get_by_level(0).unwrap().is_empty());
+        }
+
+        #[test]
+        fn best() {
+            let mut fetcher = Session::new().fetcher(Opts::default());
+            let page = mock::default_page(4);
+            let with_speed = |proxy: &Proxy, secs| Proxy {
+                time_to_connect: Duration::from_secs(secs),
+                ..proxy.clone()
+            };
+            let (slowest, fast, fastest, slow) = (
+                with_speed(&page[0], 50),
+                with_speed(&page[1], 10),
+                with_speed(&page[2], 1),
+                with_speed(&page[3], 40),
+            );
+
+            mock::push_proxies(&[slowest.clone(), fast.clone(), fastest.clone(), slow.clone()]);
+            assert_eq!(fetcher.try_get_best(2).unwrap(), [fastest, fast]);
+            // The rest are still around for later
+            assert_eq!(fetcher.try_get_best(2).unwrap(), [slow, slowest.clone()]);
+
+            // Seeds that aren't the best stay seeds
+            let seed = with_speed(&page[1], 60);
+            fetcher.prefer_seeds(vec![seed.clone(), slowest.clone()]);
+            assert_eq!(fetcher.try_get_best(1).unwrap(), [slowest]);
+            assert_eq!(fetcher.seeds, std::slice::from_ref(&seed));
+            assert!(!fetcher.buffered().contains(&seed));
+        }
+
+        #[test]
+        fn fastest() {
+            let session = Session::new();
+            let mut fetcher = session.fetcher(Opts::default());
+            let with_speed = |port, secs| Proxy {
+                socket: SocketAddr::from(([1, 2, 3, 4], port)),
+                time_to_connect: Duration::from_secs(secs),
+                ..mock::default_proxy()
+            };
+            let page = [
+                with_speed(1, 30),
+                with_speed(2, 5),
+                with_speed(3, 60),
+                with_speed(4, 1),
+                with_speed(5, 12),
+            ];
+
+            // Even a single proxy is picked from a full page
+            mock::push_proxies(&page);
+            assert_eq!(fetcher.try_get_fastest(1).unwrap(), [with_speed(4, 1)]);
+            assert_eq!(session.requests_today(), 1);
+
+            // Dropping below a page refills before sorting
+            assert_eq!(
+                fetcher.try_get_fastest(3).unwrap(),
+                [
+                    with_speed(2, 5),
+                    with_speed(5, 12),
+                    mock::nth_page(1, FREE_LIMIT).remove(0)
+                ]
+            );
+            assert_eq!(session.requests_today(), 2);
+            assert_eq!(fetcher.proxies.len(), 2 * FREE_LIMIT - 4);
+        }
+
+        #[test]
+        fn fastest_seeded() {
+            let session = Session::new();
+            let mut fetcher = session.fetcher(Opts::default());
+            let with_speed = |port, secs| Proxy {
+                socket: SocketAddr::from(([1, 2, 3, 4], port)),
+                time_to_connect: Duration::from_secs(secs),
+                ..mock::default_proxy()
+            };
+            let (slow_seed, fast_seed) = (with_speed(1, 90), with_speed(2, 1));
+            fetcher.prefer_seeds(vec![slow_seed.clone(), fast_seed.clone()]);
+
+            // Seeds are ranked with the page, but the slow one is still a seed afterwards
+            assert_eq!(fetcher.try_get_fastest(1).unwrap(), [fast_seed]);
+            assert_eq!(session.requests_today(), 1);
+            assert_eq!(fetcher.seeds, std::slice::from_ref(&slow_seed));
+            assert!(!fetcher.buffered().contains(&slow_seed));
+            // so it's served first just like `prefer_seeds` promises
+            assert_eq!(fetcher.try_get(1).unwrap(), [slow_seed]);
+        }
+
+        #[test]
+        fn supporting() {
+            let mut fetcher = Session::new().fetcher(Opts::default());
+            let with = |cookies, referer| Proxy {
+                socket: SocketAddr::from(([1, 2, 3, 4], 2 * cookies as u16 + referer as u16)),
+                supports: Supports {
+                    cookies,
+                    referer,
+                    ..Supports::default()
+                },
+                ..mock::default_proxy()
+            };
+            let filter = SupportsFilter {
+                required: Supports {
+                    cookies: true,
+                    ..Supports::default()
+                },
+                preferred: Supports {
+                    referer: true,
+                    ..Supports::default()
+                },
+            };
+
+            // Proxies without cookies are excluded and referer support is ranked first
+            mock::push_proxies(&[with(false, true), with(true, false)]);
+            mock::push_proxies(&[with(true, true), with(false, false)]);
+            let proxies = fetcher.try_get_supporting(2, &filter).unwrap();
+            assert_eq!(proxies, [with(true, true), with(true, false)]);
+            assert_eq!(fetcher.session.requests_today(), 2);
+            // and the excluded proxies are still around
+            assert_eq!(fetcher.proxies, [with(false, true), with(false, false)]);
+
+            // The default proxies never support cookies
+            assert!(matches!(
+                fetcher.try_get_supporting(1, &filter),
+                Err(ApiError::AttemptsExhausted { attempts }) if attempts == constants::MAX_ATTEMPTS
+            ));
+        }
+
+        #[test]
+        fn try_get_none() {
+            let session = Session::new().fresh_after(Instant::now());
+            let mut fetcher = session.fetcher(Opts::default());
+
+            // No request and no waiting on the delay
+            let start = Instant::now();
+            assert_eq!(fetcher.try_get(0).unwrap(), Vec::new());
+            assert!(start.elapsed() < constants::DELAY);
+            assert_eq!(session.requests_today(), 0);
+            assert!(fetcher.proxies.is_empty());
+        }
+
+        #[test]
+        fn exhausted() {
+            let mut fetcher = Session::new().fetcher(Opts::default());
+
+            // An empty page stops the fetcher instead of requesting forever
+            mock::push_proxies(&[mock::default_proxy()]);
+            mock::push_proxies(&[]);
+            assert!(matches!(fetcher.try_get(2), Err(ApiError::Exhausted)));
+            assert_eq!(fetcher.session.requests_today(), 2);
+            // and whatever was found is still there
+            assert_eq!(fetcher.proxies, [mock::default_proxy()]);
+
+            // The same goes for pages that only have recently failed proxies
+            fetcher.report_failure(&mock::default_proxy());
+            mock::push_proxies(&[mock::default_proxy()]);
+            assert!(matches!(fetcher.try_get(1), Err(ApiError::Exhausted)));
+        }
+
+        #[test]
+        fn filtered() {
+            let mut fetcher = Session::new().fetcher(Opts::default());
+            let with = |port, https, cookies| Proxy {
+                socket: SocketAddr::from(([1, 2, 3, 4], port)),
+                supports: Supports {
+                    https,
+                    cookies,
+                    ..Supports::default()
+                },
+                ..mock::default_proxy()
+            };
+            let secure = |proxy: &Proxy| proxy.supports.https && proxy.supports.cookies;
+
+            mock::push_proxies(&[with(1, true, false), with(2, true, true)]);
+            mock::push_proxies(&[with(3, false, true), with(4, true, true)]);
+            let proxies = fetcher.try_get_filtered(2, secure).unwrap();
+            assert_eq!(proxies, [with(2, true, true), with(4, true, true)]);
+            assert_eq!(fetcher.session.requests_today(), 2);
+            // Non-matching proxies are kept around
+            assert_eq!(
+                fetcher.proxies,
+                [with(1, true, false), with(3, false, true)]
+            );
+
+            // The default proxies never match so this gives up
+            assert!(matches!(
+                fetcher.try_get_filtered(1, secure),
+                Err(ApiError::AttemptsExhausted { attempts }) if attempts == constants::MAX_ATTEMPTS
+            ));
+        }
+
+        #[test]
+        fn keep_raw() {
+            let session = Session::new();
+            let page = |port| Proxy {
+                socket: SocketAddr::from(([1, 2, 3, 4], port)),
+                ..mock::default_proxy()
+            };
+
+            // Nothing is kept by default
+            let mut fetcher = session.fetcher(Opts::default());
+            fetcher.try_get(1).unwrap();
+            assert!(fetcher.last_raw_response().is_none());
+
+            let mut fetcher = session.fetcher(Opts::default()).keep_raw(true);
+            mock::push_proxies(&[page(1)]);
+            fetcher.try_get(1).unwrap();
+            let raw = fetcher.last_raw_response().unwrap();
+            assert_eq!(proxies_from_json(raw).unwrap(), [page(1)]);
+
+            // Only the most recent successful response is kept
+            mock::push_proxies(&[page(2)]);
+            mock::push_response(200, "No proxy");
+            fetcher.try_get(1).unwrap();
+            assert!(fetcher.try_get(1).is_err());
+            let raw = fetcher.last_raw_response().unwrap();
+            assert_eq!(proxies_from_json(raw).unwrap(), [page(2)]);
+        }
+
+        #[test]
+        fn intra_page_dedup() {
+            let session = Session::new();
+            let page = mock::default_page(2);
+            let duplicated = [page[0].clone(), page[1].clone(), page[0].clone()];
+
+            // Only the first of each socket is kept by default
+            let mut fetcher = session.fetcher(Opts::default());
+            mock::push_proxies(&duplicated);
+            assert_eq!(fetcher.fetch_page().unwrap().proxies, page);
+
+            // but the page can also be left as is
+            let mut fetcher = session
+                .fetcher(Opts::default())
+                .with_intra_page_dedup(IntraPageDedup::Keep);
+            mock::push_proxies(&duplicated);
+            assert_eq!(fetcher.fetch_page().unwrap().proxies, duplicated);
+
+            // Duplicates across separate pages are handled by the buffer dedup instead
+            let mut fetcher = session
+                .fetcher(Opts::default())
+                .with_intra_page_dedup(IntraPageDedup::Keep)
+                .with_buffer_dedup(false);
+            mock::push_proxies(&page);
+            mock::push_proxies(&page);
+            assert_eq!(fetcher.try_get(4).unwrap().len(), 4);
+        }
+
+        #[test]
+        fn validated() {
+            let session = Session::new();
+            let mut fetcher = session.fetcher(Opts::default());
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let alive = Proxy {
+                socket: listener.local_addr().unwrap(),
+                ..mock::default_proxy()
+            };
+            // Nothing is listening once the listener is dropped
+            let dead = || {
+                let closed = TcpListener::bind("127.0.0.1:0").unwrap();
+                Proxy {
+                    socket: closed.local_addr().unwrap(),
+                    ..mock::default_proxy()
+                }
+            };
+
+            // Dead proxies get thrown out and reported
+            let first_dead = dead();
+            mock::push_proxies(&[alive.clone(), first_dead.clone()]);
+            assert_eq!(fetcher.try_get_validated(1).unwrap(), vec![alive.clone()]);
+            assert!(fetcher.proxies.is_empty());
+            assert!(fetcher.failures.contains_key(&first_dead.socket));
+
+            // and the API keeps getting asked until there are enough live proxies
+            mock::push_proxies(&[dead()]);
+            mock::push_proxies(std::slice::from_ref(&alive));
+            assert_eq!(fetcher.try_get_validated(1).unwrap(), [alive]);
+            assert_eq!(session.requests_today(), 3);
+        }
+
+        #[cfg(feature = "log")]
+        #[test]
+        fn logging() {
+            mock::init_logger();
+            let session = Session::new();
+            let logged = |needle: &str| mock::logs().iter().any(|log| log.contains(needle));
+
+            // The api key is redacted from the logged url
+            let opts = Opts::builder().api_key("hunter2".to_string()).build();
+            let mut fetcher = session.fetcher(opts).with_uri("http://logging.test/api?");
+            fetcher.try_get(1).unwrap();
+            assert!(logged(
+                "DEBUG Requesting http://logging.test/api?api=%3Credacted%3E"
+            ));
+            assert!(logged("DEBUG Got 20 proxies from http://logging.test/api?"));
+            assert!(!logged("hunter2"));
+
+            // and keyless requests log how long they wait
+            let mut fetcher = session
+                .fetcher(Opts::default())
+                .with_uri("http://keyless.test/api?");
+            fetcher.warm().unwrap();
+            fetcher.warm().unwrap();
+            assert!(logged("DEBUG Waiting"));
+        }
+
+        #[test]
+        fn buffer_dedup() {
+            let session = Session::new();
+            let page = mock::default_page(3);
+
+            // Proxies that are already buffered get skipped from later pages
+            let mut fetcher = session.fetcher(Opts::default());
+            mock::push_proxies(&page[..2]);
+            mock::push_proxies(&page[1..]);
+            let mut proxies = fetcher.try_get(3).unwrap();
+            proxies.sort_by_key(|proxy| proxy.socket);
+            assert_eq!(proxies, page);
+            assert!(fetcher.drain().is_empty());
+
+            // A page of nothing but repeats doesn't count as progress
+            let mut fetcher = session.fetcher(Opts::default());
+            mock::push_proxies(&page);
+            mock::push_proxies(&page);
+            assert!(matches!(fetcher.try_get(4), Err(ApiError::Exhausted)));
+            assert_eq!(fetcher.drain(), page);
+
+            // but the raw counts can still be kept
+            let mut fetcher = session.fetcher(Opts::default()).with_buffer_dedup(false);
+            mock::push_proxies(&page);
+            mock::push_proxies(&page);
+            assert_eq!(fetcher.try_get(4).unwrap().len(), 4);
+            assert_eq!(fetcher.drain().len(), 2);
+        }
+
+        #[test]
+        fn prefetch() {
+            let session = Session::new();
+            let mut fetcher = session.fetcher(Opts::default()).with_prefetch_below(3);
+            let wait_for_requests = |requests| {
+                let start = Instant::now();
+                while session.requests_today() < requests {
+                    assert!(
+                        start.elapsed() < Duration::from_secs(5),
+                        "No prefetch happened"
+                    );
+                    thread::sleep(Duration::from_millis(10));
+                }
+            };
+
+            // Staying at or above the threshold doesn't prefetch
+            assert_eq!(fetcher.try_get(2).unwrap().len(), 2);
+            thread::sleep(constants::DELAY * 2);
+            assert_eq!(session.requests_today(), 1);
+
+            // But dropping below it does
+            assert_eq!(fetcher.try_get(2).unwrap().len(), 2);
+            wait_for_requests(2);
+
+            // and the prefetched page is used instead of making another request
+            assert_eq!(fetcher.try_get(5).unwrap().len(), 5);
+            assert_eq!(fetcher.proxies.len(), 1);
+            wait_for_requests(3);
+            assert_eq!(fetcher.drain().len(), 1);
+            assert_eq!(session.requests_today(), 3);
+        }
+
+        #[test]
+        fn pending_prefetch() {
+            let session = Session::new();
+            let mut fetcher = session.fetcher(Opts::default()).with_prefetch_below(5);
+
+            // Leaves a prefetch waiting out the keyless delay in the background
+            assert_eq!(fetcher.try_get(2).unwrap().len(), 2);
+            assert!(fetcher.prefetch.as_ref().unwrap().pending.is_some());
+
+            // which doesn't hold up proxies that are already buffered
+            let start = Instant::now();
+            assert_eq!(fetcher.try_get(1).unwrap().len(), 1);
+            assert!(
+                start.elapsed() < constants::DELAY / 2,
+                "{:?}",
+                start.elapsed()
+            );
+            assert!(fetcher.prefetch.as_ref().unwrap().pending.is_some());
+
+            // It still gets waited on once the buffer runs short, instead of making another request
+            assert_eq!(fetcher.try_get(5).unwrap().len(), 5);
+            assert_eq!(session.requests_today(), 2);
+        }
+
+        #[test]
+        fn verbose() {
+            let opts = Opts::builder()
+                .level(Level::Elite)
+                .protocol(Protocol::Http)
+                .https(true)
+                .build();
+            let mut fetcher = Session::new().fetcher(opts);
+
+            let honest = |port| {
+                Proxy::builder()
+                    .socket(SocketAddr::from(([1, 2, 3, 4], port)))
+                    .country(Country::US)
+                    .level(Level::Elite)
+                    .protocol(Protocol::Http)
+                    .supports(Supports {
+                        https: true,
+                        ..Supports::default()
+                    })
+                    .build()
+            };
+            let anonymous = Proxy {
+                level: Level::Anonymous,
+                ..honest(2)
+            };
+            let socks_without_https = Proxy {
+                protocol: Protocol::Socks5,
+                supports: Supports::default(),
+                ..honest(3)
+            };
+            mock::push_proxies(&[honest(1), anonymous, socks_without_https]);
+
+            let (proxies, report) = fetcher.try_get_verbose(3).unwrap();
+            assert_eq!(proxies.len(), 3);
+            assert_eq!(report.checked, 3);
+            assert!(!report.is_clean());
+            let names: Vec<_> = report.options.iter().map(|option| option.name).collect();
+            assert_eq!(names, ["level", "protocol", "https"]);
+            assert_eq!(report.violations("level"), 1);
+            assert_eq!(report.violations("protocol"), 1);
+            assert_eq!(report.violations("https"), 1);
+            assert_eq!(report.options[0].matched, 2);
+            // Options that weren't set are never violated
+            assert_eq!(report.violations("post"), 0);
+
+            // Nothing to violate without any options
+            let mut fetcher = Session::new().fetcher(Opts::default());
+            let (_, report) = fetcher.try_get_verbose(2).unwrap();
+            assert_eq!(report.checked, 2);
+            assert!(report.options.is_empty());
+            assert!(report.is_clean());
+        }
+
+        #[test]
+        fn custom_uri() {
+            let opts = Opts::default();
+            let url = |fetcher: Fetcher| {
+                fetcher
+                    .request_builder(&opts)
+                    .unwrap()
+                    .get_url()
+                    .to_string()
+            };
+            assert_eq!(
+                url(Session::new().fetcher(opts.clone())),
+                constants::API_URI
             );
 
-            let single = default.try_get(1).unwrap();
-            let double = premium.try_get(2).unwrap();
-            let triple = custom.try_get(3).unwrap();
-            assert_eq!(single.len(), 1);
-            assert_eq!(double.len(), 2);
-            assert_eq!(triple.len(), 3);
-            assert_eq!(default.drain().len(), FREE_LIMIT - single.len());
-            assert_eq!(premium.drain().len(), PREMIUM_LIMIT - double.len());
-            assert_eq!(custom.drain().len(), FREE_LIMIT - triple.len());
+            // Fetchers start with their session's uri, but can each use their own
+            let session = Session::new().with_uri("http://localhost:8080/api/proxy");
+            assert_eq!(
+                url(session.fetcher(opts.clone())),
+                "http://localhost:8080/api/proxy"
+            );
+            let local = session
+                .fetcher(opts.clone())
+                .with_uri("http://127.0.0.1:1234/proxy");
+            assert_eq!(url(local), "http://127.0.0.1:1234/proxy");
+        }
+
+        #[test]
+        fn warm() {
+            let session = Session::new();
+            let mut fetcher = session.fetcher(Opts::default());
+            fetcher.proxies = vec![Proxy {
+                socket: SocketAddr::from(([1, 2, 3, 4], 1)),
+                ..mock::proxy_in(Country::JP)
+            }];
+
+            // A full page gets added even though the list wasn't empty
+            assert_eq!(fetcher.warm().unwrap(), FREE_LIMIT);
+            assert_eq!(fetcher.proxies.len(), FREE_LIMIT + 1);
+
+            // so the next `try_get` doesn't need to wait on the API
+            let start = Instant::now();
+            assert_eq!(
+                fetcher.try_get(FREE_LIMIT + 1).unwrap().len(),
+                FREE_LIMIT + 1
+            );
+            assert!(start.elapsed() < constants::DELAY);
+
+            // Warming again still respects the delay
+            let start = Instant::now();
+            assert_eq!(fetcher.warm().unwrap(), FREE_LIMIT);
+            assert!(start.elapsed() >= constants::DELAY);
+        }
+
+        #[test]
+        fn force_keyless() {
+            let session = Session::new();
+            let opts = Opts::builder().api_key("<key>".to_string()).build();
+
+            // Premium fetchers normally go back to back
+            let mut premium = session.fetcher(opts.clone());
+            let start = Instant::now();
+            premium.fetch_page().unwrap();
+            premium.fetch_page().unwrap();
+            assert!(start.elapsed() < constants::DELAY);
+
+            // but can be made to wait like keyless ones
+            let mut forced = session.fetcher(opts);
+            forced.force_keyless();
+            assert!(forced.opts.is_premium());
+            let start = Instant::now();
+            assert_eq!(forced.fetch_page().unwrap().proxies.len(), FREE_LIMIT);
+            assert_eq!(forced.fetch_page().unwrap().proxies.len(), FREE_LIMIT);
+            assert!(start.elapsed() >= constants::DELAY);
+            assert!(forced.snapshot().time_until_ready > Duration::from_secs(0));
+        }
+
+        #[test]
+        fn burst() {
+            let session = Session::new();
+            let mut fetcher = session
+                .fetcher(Opts::default())
+                .with_output_rate(NonZeroU32::new(1).unwrap(), Duration::from_secs(60))
+                .with_prefetch_below(5);
+            fetcher.proxies = vec![mock::default_proxy(); 3];
+            mock::push_proxies(&[mock::proxy_in(Country::JP)]);
+
+            // Everything that's buffered comes out right away, ignoring the output rate
+            let start = Instant::now();
+            assert_eq!(fetcher.try_burst(5).len(), 3);
+            assert!(fetcher.try_burst(5).is_empty());
+            assert!(start.elapsed() < constants::DELAY);
+            // And the API was never touched, not even to prefetch
+            assert!(fetcher.prefetch.as_ref().unwrap().pending.is_none());
+            assert_eq!(session.requests_today(), 0);
+            // So the queued response is still there for the next real request
+            fetcher.fill(1).unwrap();
+            assert_eq!(fetcher.proxies, [mock::proxy_in(Country::JP)]);
+        }
+
+        #[test]
+        fn allocation() {
+            assert_eq!(allocate(10, &[0.7, 0.3]).unwrap(), [7, 3]);
+            assert_eq!(allocate(10, &[1.0, 1.0, 1.0]).unwrap(), [4, 3, 3]);
+            assert_eq!(allocate(3, &[0.5, 0.3, 0.2]).unwrap(), [1, 1, 1]);
+            // Weights don't need to add up to anything in particular
+            assert_eq!(allocate(5, &[7.0, 3.0]).unwrap(), [4, 1]);
+            // Ignored weights don't get anything
+            assert_eq!(
+                allocate(4, &[-1.0, 1.0, f64::NAN, 0.0]).unwrap(),
+                [0, 4, 0, 0]
+            );
+            // Unless there's nothing to go off of at all
+            assert_eq!(allocate(4, &[-1.0, f64::NAN, 0.0]), None);
+            assert_eq!(allocate(4, &[]), None);
+
+            for amount in 0..50 {
+                let shares = allocate(amount, &[0.15, 0.35, 0.2, 0.3]).unwrap();
+                assert_eq!(shares.iter().sum::<usize>(), amount);
+            }
+        }
+
+        #[test]
+        fn distributed() {
+            let mut fetcher = Session::new().fetcher(Opts::default());
+
+            mock::push_proxies(&mock::nth_page_in(Country::US, 0, 5));
+            mock::push_proxies(&mock::nth_page_in(Country::US, 1, 5));
+            mock::push_proxies(&mock::nth_page_in(Country::DE, 2, 5));
+            let proxies = fetcher
+                .try_get_distributed(10, &[(Country::US, 0.7), (Country::DE, 0.3)])
+                .unwrap();
+
+            let count = |country| proxies.iter().filter(|p| p.country == country).count();
+            assert_eq!(proxies.len(), 10);
+            assert_eq!(count(Country::US), 7);
+            assert_eq!(count(Country::DE), 3);
+            // The extras are still around
+            assert_eq!(fetcher.buffered_in(Country::US).count(), 3);
+            assert_eq!(fetcher.buffered_in(Country::DE).count(), 2);
+
+            // Weights that can't be split between are an error instead of a panic
+            assert!(matches!(
+                fetcher.try_get_distributed(1, &[(Country::US, 0.0)]),
+                Err(ApiError::NoPositiveWeight)
+            ));
+            // while asking for nothing never gets that far
+            let requests = fetcher.session.requests_today();
+            assert!(fetcher.try_get_distributed(0, &[]).unwrap().is_empty());
+            assert_eq!(fetcher.session.requests_today(), requests);
+        }
+
+        #[test]
+        fn diverse() {
+            let session = Session::new();
+            let mut fetcher = session.fetcher(Opts::default());
+            let countries = |proxies: &[Proxy]| -> HashSet<_> {
+                proxies.iter().map(|proxy| proxy.country).collect()
+            };
+
+            // Keeps requesting until there are enough countries (the last page is the default CA)
+            mock::push_proxies(&mock::page_in(Country::US, 5));
+            let elsewhere = |country, port| Proxy {
+                socket: SocketAddr::from(([1, 2, 3, 4], port)),
+                ..mock::proxy_in(country)
+            };
+            mock::push_proxies(&[elsewhere(Country::DE, 1), elsewhere(Country::US, 2)]);
+            mock::push_proxies(&mock::nth_page_in(Country::JP, 2, 5));
+            let proxies = fetcher.try_get_diverse(4).unwrap();
+            assert_eq!(session.requests_today(), 4);
+            assert_eq!(
+                countries(&proxies),
+                [Country::US, Country::DE, Country::JP, Country::CA]
+                    .iter()
+                    .copied()
+                    .collect()
+            );
+
+            // Enough countries are already around so no requests are needed
+            let proxies = fetcher.try_get_diverse(3).unwrap();
+            assert_eq!(session.requests_today(), 4);
+            assert_eq!(countries(&proxies).len(), 3);
+
+            // And it gives up on finding more once a page doesn't have any new countries
+            let mut fetcher = session.fetcher(Opts::default());
+            mock::push_proxies(&mock::page_in(Country::US, 5));
+            let proxies = fetcher.try_get_diverse(5).unwrap();
+            assert_eq!(session.requests_today(), 7);
+            assert_eq!(proxies.len(), 5);
+            assert_eq!(
+                countries(&proxies),
+                [Country::US, Country::CA].iter().copied().collect()
+            );
+        }
+
+        #[test]
+        fn fresh_iter() {
+            let mut fetcher = Session::new().fetcher(Opts::default());
+            // The default proxies were checked ages ago
+            let stale = mock::default_page(5);
+            let fresh = |port| Proxy {
+                socket: SocketAddr::from(([1, 2, 3, 4], port)),
+                last_checked: Utc::now().naive_utc(),
+                ..mock::default_proxy()
+            };
+
+            mock::push_proxies(&[stale[0].clone(), fresh(1), stale[1].clone()]);
+            mock::push_proxies(&stale[2..4]);
+            mock::push_proxies(&[fresh(2), stale[4].clone(), fresh(3)]);
+            let ports: Vec<_> = fetcher
+                .fresh_iter(Duration::from_secs(60))
+                .take(3)
+                .map(|proxy| proxy.unwrap().socket.port())
+                .collect();
+            assert_eq!(ports, [1, 2, 3]);
+            // The stale proxies were skipped, but not thrown out
+            assert_eq!(fetcher.proxies, stale);
+
+            // Only stale proxies from here on out
+            let mut iter = fetcher.fresh_iter(Duration::from_secs(60));
+            assert!(matches!(
+                iter.next(),
+                Some(Err(ApiError::AttemptsExhausted { .. }))
+            ));
+            assert!(iter.next().is_none());
+        }
+
+        #[test]
+        fn iter() {
+            let mut fetcher = Session::new().fetcher(Opts::default());
+
+            // Each proxy is taken from the end of the list with refills as needed
+            let ports: Vec<_> = fetcher
+                .iter()
+                .take(FREE_LIMIT + 2)
+                .map(|proxy| proxy.unwrap().socket.port())
+                .collect();
+            assert_eq!(ports, [4325, 4324, 4323, 4322, 4321, 4345, 4344]);
+            assert_eq!(fetcher.session.requests_today(), 2);
+            assert_eq!(fetcher.proxies.len(), FREE_LIMIT - 2);
+
+            // Iteration stops after an error
+            fetcher.proxies.clear();
+            mock::push_proxies(&[]);
+            let mut iter = fetcher.iter();
+            assert!(matches!(iter.next(), Some(Err(ApiError::Exhausted))));
+            assert!(iter.next().is_none());
+        }
+
+        #[test]
+        fn seeds() {
+            let session = Session::new();
+            let mut fetcher = session.fetcher(Opts::default());
+            let seed = |port| Proxy {
+                socket: SocketAddr::from(([4, 3, 2, 1], port)),
+                ..mock::default_proxy()
+            };
+
+            // Seeds are served in order without needing any requests
+            fetcher.prefer_seeds(vec![seed(1), seed(2), seed(3)]);
+            assert_eq!(fetcher.try_get(2).unwrap(), [seed(1), seed(2)]);
+            assert_eq!(session.requests_today(), 0);
+
+            // Then the API fills in the rest once they run out
+            let proxies = fetcher.try_get(3).unwrap();
+            assert_eq!(proxies[0], seed(3));
+            assert_eq!(&proxies[1..], &mock::default_page(FREE_LIMIT)[3..]);
+            assert_eq!(session.requests_today(), 1);
+            assert_eq!(fetcher.drain().len(), FREE_LIMIT - 2);
+        }
+
+        #[test]
+        fn report_failure() {
+            let ttl = Duration::from_secs(60);
+            let mut fetcher = Session::new()
+                .fetcher(Opts::default())
+                .with_failure_ttl(ttl);
+            let failed = Proxy {
+                socket: "4.3.2.1:1234".parse().unwrap(),
+                ..mock::default_proxy()
+            };
+
+            // Reporting a failure removes it from the internal list
+            fetcher.proxies = vec![failed.clone(), mock::default_proxy()];
+            let reported_at = Instant::now();
+            fetcher.report_failure_at(&failed, reported_at);
+            assert_eq!(fetcher.proxies, [mock::default_proxy()]);
+
+            // and from any new responses
+            let other = Proxy {
+                socket: "1.2.3.4:1".parse().unwrap(),
+                ..mock::default_proxy()
+            };
+            mock::push_proxies(&[failed.clone(), failed.clone(), other.clone()]);
+            assert_eq!(
+                fetcher.try_get(2).unwrap(),
+                vec![mock::default_proxy(), other]
+            );
+
+            // Once the TTL has passed it's fine to use again
+            let expired = reported_at + ttl;
+            let proxies = fetcher.eligible(vec![failed.clone()], expired);
+            assert_eq!(proxies, vec![failed.clone()]);
+
+            // Reporting another failure afterwards clears out the expired one
+            fetcher.report_failure_at(&mock::default_proxy(), expired);
+            assert_eq!(fetcher.failures.len(), 1);
+            mock::push_proxies(&[failed.clone(), mock::default_proxy()]);
+            assert_eq!(fetcher.fetch_page().unwrap().proxies, [failed]);
         }
     }
 
     mod delays {
-        use std::time::Duration;
+        use std::{thread, time::Duration};
 
         use super::*;
 
@@ -272,8 +3053,8 @@ mod tests {
 
         // TODO: do this with a fixture
         fn reset_last_fetched() {
-            let mut last_fetched = LAST_FETCHED.lock().unwrap();
-            *last_fetched = Instant::now() - constants::DELAY;
+            let mut last_fetched = GLOBAL.last_fetched.lock().unwrap();
+            *last_fetched = None;
         }
 
         // Helper function for ensuring runtime of a `FnOnce`
@@ -303,7 +3084,7 @@ mod tests {
                 || {
                     reset_last_fetched();
                     let mut fetcher = Fetcher::default();
-                    let _ = fetcher.try_get(FREE_LIMIT);
+                    fetcher.try_get(FREE_LIMIT).unwrap();
                     fetcher
                 },
                 // 10ms +/- 10ms
@@ -313,7 +3094,7 @@ mod tests {
             // any more will take another call
             let mut fetcher = time_it(
                 || {
-                    let _ = fetcher.try_get(1);
+                    fetcher.try_get(1).unwrap();
                     fetcher
                 },
                 // delay +/- 10ms
@@ -323,7 +3104,7 @@ mod tests {
             // and since there are proxies in the internal list we can just use those
             time_it(
                 || {
-                    let _ = fetcher.try_get(1);
+                    fetcher.try_get(1).unwrap();
                     assert!(!fetcher.drain().is_empty());
                 },
                 // 10ms +/- 10ms
@@ -345,10 +3126,10 @@ mod tests {
                     let mut premium =
                         Fetcher::new(Opts::builder().api_key("<key>".to_string()).build());
 
-                    let _ = keyless1.try_get(2 * FREE_LIMIT);
+                    keyless1.try_get(2 * FREE_LIMIT).unwrap();
                     // Even while the keyless ones would be delayed, the premium is not
-                    let _ = premium.try_get(2 * PREMIUM_LIMIT);
-                    let _ = keyless2.try_get(2 * FREE_LIMIT);
+                    premium.try_get(2 * PREMIUM_LIMIT).unwrap();
+                    keyless2.try_get(2 * FREE_LIMIT).unwrap();
                 },
                 // 3 * delay +/- 10ms
                 (3 * constants::DELAY, TEN_MILLISEC),
@@ -366,8 +3147,8 @@ mod tests {
                     let mut fetcher1 = Fetcher::default();
                     let mut fetcher2 = Fetcher::default();
 
-                    let _ = fetcher1.try_get(1);
-                    let _ = fetcher2.try_get(1);
+                    fetcher1.try_get(1).unwrap();
+                    fetcher2.try_get(1).unwrap();
 
                     (fetcher1, fetcher2)
                 },
@@ -378,8 +3159,8 @@ mod tests {
             // And each fetcher should now have an internal list to pull from with no delay
             time_it(
                 || {
-                    let _ = fetcher1.try_get(1);
-                    let _ = fetcher2.try_get(1);
+                    fetcher1.try_get(1).unwrap();
+                    fetcher2.try_get(1).unwrap();
                     assert!(!fetcher1.drain().is_empty());
                     assert!(!fetcher2.drain().is_empty());
                 },
@@ -400,11 +3181,11 @@ mod tests {
                     let mut fetcher2 = Fetcher::default();
 
                     let handle1 = thread::spawn(move || {
-                        let _ = fetcher1.try_get(1);
+                        fetcher1.try_get(1).unwrap();
                         assert!(!fetcher1.drain().is_empty());
                     });
                     let handle2 = thread::spawn(move || {
-                        let _ = fetcher2.try_get(1);
+                        fetcher2.try_get(1).unwrap();
                         assert!(!fetcher2.drain().is_empty());
                     });
 
@@ -20,8 +20,9 @@
 //!
 //! Without an API key pubproxy limits users to one request per second so a `Fetcher` will try to
 //! ensure that at most only one request per second is done without an API key. This is synchronized
-//! between fetchers including across different threads: however, there can still be issues from
-//! running multiple programs from the same IP causing rate limiting to occur. The rate-limiting is
+//! between fetchers from the same [`Session`][session::Session] including across different threads:
+//! however, there can still be issues from running multiple programs (or multiple `Session`s) from
+//! the same IP causing rate limiting to occur. The rate-limiting is
 //! quite severe (will deny requests for potentially several hours), so it's best to avoid by all
 //! means possible.
 //!
@@ -72,9 +73,22 @@
 #[macro_use]
 extern crate lazy_static;
 
+#[macro_use]
+mod logging;
+
+#[cfg(feature = "async")]
+pub mod async_fetcher;
 mod constants;
 pub mod errors;
+pub mod feed;
 pub mod fetcher;
+#[cfg(test)]
+mod mock;
 pub mod opts;
+pub mod policy;
+pub mod pool;
 pub mod proxy;
+pub mod session;
+mod throttle;
+pub mod tiered;
 pub mod types;
@@ -0,0 +1,38 @@
+//! Internal logging macros.
+//!
+//! These forward to the `log` crate when the `log` feature is enabled and do nothing otherwise, so
+//! the rest of the crate can log without caring which it is.
+
+#[cfg(feature = "log")]
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        ::log::debug!($($arg)+)
+    };
+}
+
+#[cfg(feature = "log")]
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        ::log::warn!($($arg)+)
+    };
+}
+
+// The arguments are still type-checked so that anything only used for logging isn't left unused,
+// but they're never evaluated so nothing is spent building them
+#[cfg(not(feature = "log"))]
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        if false {
+            let _ = format_args!($($arg)+);
+        }
+    };
+}
+
+#[cfg(not(feature = "log"))]
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        if false {
+            let _ = format_args!($($arg)+);
+        }
+    };
+}
@@ -0,0 +1,194 @@
+//! Internal mocking of API responses for tests.
+//!
+//! Responses are queued per-thread, and any fetch that happens while the queue is empty gets a
+//! default page of proxies instead.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    net::SocketAddr,
+    thread,
+    time::Duration,
+};
+
+use crate::{proxy::Proxy, types::NaiveResponse};
+
+use chrono::NaiveDate;
+use iso_country::Country;
+use serde_json::{json, Value};
+
+thread_local! {
+    static RESPONSES: RefCell<VecDeque<NaiveResponse>> = const { RefCell::new(VecDeque::new()) };
+    static DELAY: Cell<Duration> = const { Cell::new(Duration::from_secs(0)) };
+}
+
+#[cfg(feature = "log")]
+pub use logger::{init_logger, logs};
+
+// Captures everything logged through the `log` crate for checking in tests
+#[cfg(feature = "log")]
+mod logger {
+    use std::sync::{Mutex, Once};
+
+    use log::{Log, Metadata, Record};
+
+    lazy_static! {
+        static ref LOGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    }
+
+    struct Logger;
+
+    impl Log for Logger {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            LOGS.lock()
+                .unwrap()
+                .push(format!("{} {}", record.level(), record.args()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// Installs a logger that captures all logs. Logs are shared between every test unlike the
+    /// queued responses
+    pub fn init_logger() {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            log::set_logger(&Logger).unwrap();
+            log::set_max_level(log::LevelFilter::Trace);
+        });
+    }
+
+    /// Everything that's been logged since `init_logger` was called
+    pub fn logs() -> Vec<String> {
+        LOGS.lock().unwrap().clone()
+    }
+}
+
+/// The proxy that makes up the default page
+pub fn default_proxy() -> Proxy {
+    Proxy::builder()
+        .socket(SocketAddr::from(([1, 2, 3, 4], 4321)))
+        .country(Country::CA)
+        .last_checked(
+            NaiveDate::from_ymd_opt(2020, 1, 1)
+                .unwrap()
+                .and_hms_opt(1, 1, 1)
+                .unwrap(),
+        )
+        .time_to_connect(Duration::from_secs(21))
+        .build()
+}
+
+/// A page of `len` default proxies that each have a different port, starting with the default
+/// proxy itself
+pub fn default_page(len: usize) -> Vec<Proxy> {
+    nth_page(0, len)
+}
+
+/// The fallback page for the `nth` request from a session. Each page gets its own range of ports
+/// so that pages never repeat each other, with the first one being the `default_page`
+pub fn nth_page(nth: usize, len: usize) -> Vec<Proxy> {
+    // Wide enough for a premium page, and wrapping well before running out of ports
+    let first_port = default_proxy().socket.port() as usize + (nth % 3_000) * 20;
+    let default = default_proxy();
+    (0..len)
+        .map(|i| Proxy {
+            socket: SocketAddr::new(default.socket.ip(), (first_port + i) as u16),
+            ..default.clone()
+        })
+        .collect()
+}
+
+/// A `default_page`, but with every proxy located in `country`
+pub fn page_in(country: Country, len: usize) -> Vec<Proxy> {
+    nth_page_in(country, 0, len)
+}
+
+/// An `nth_page`, but with every proxy located in `country`
+pub fn nth_page_in(country: Country, nth: usize, len: usize) -> Vec<Proxy> {
+    nth_page(nth, len)
+        .into_iter()
+        .map(|proxy| Proxy { country, ..proxy })
+        .collect()
+}
+
+/// The default proxy, but located in `country`
+pub fn proxy_in(country: Country) -> Proxy {
+    Proxy {
+        country,
+        ..default_proxy()
+    }
+}
+
+/// Queues up a raw response for the next fetch on this thread
+pub fn push_response(status: u16, text: &str) {
+    RESPONSES.with(|responses| {
+        responses
+            .borrow_mut()
+            .push_back(NaiveResponse::new(status, text.to_string()))
+    });
+}
+
+/// Queues up a rate limited response that asks to retry after `retry_after`
+pub fn push_retry_after(retry_after: Duration) {
+    RESPONSES.with(|responses| {
+        responses.borrow_mut().push_back(NaiveResponse {
+            retry_after: Some(retry_after),
+            ..NaiveResponse::new(429, "Too Many Requests".to_string())
+        })
+    });
+}
+
+/// Queues up a successful response containing `proxies` for the next fetch on this thread. Note
+/// that proxies with an unspecified country get filtered out when parsing.
+pub fn push_proxies(proxies: &[Proxy]) {
+    push_response(200, &page(proxies));
+}
+
+/// Makes the next fetch on this thread take at least `delay` to respond
+pub fn delay_next(delay: Duration) {
+    DELAY.with(|next| next.set(delay));
+}
+
+/// Pops the next queued response, falling back to a full `nth_page` where `nth` is the number of
+/// requests that the session made before this one
+pub fn response(limit: usize, nth: usize) -> NaiveResponse {
+    thread::sleep(DELAY.with(|delay| delay.take()));
+
+    RESPONSES
+        .with(|responses| responses.borrow_mut().pop_front())
+        .unwrap_or_else(|| NaiveResponse::new(200, page(&nth_page(nth, limit))))
+}
+
+fn page(proxies: &[Proxy]) -> String {
+    let data: Vec<_> = proxies.iter().map(raw_proxy).collect();
+    json!({ "data": data, "count": data.len() }).to_string()
+}
+
+fn raw_proxy(proxy: &Proxy) -> Value {
+    let flag = |supported| if supported { 1 } else { 0 };
+
+    json!({
+        "ipPort": proxy.socket.to_string(),
+        "ip": proxy.socket.ip().to_string(),
+        "port": proxy.socket.port().to_string(),
+        "country": proxy.country.to_string(),
+        "last_checked": proxy.last_checked.format("%F %T").to_string(),
+        "proxy_level": proxy.level,
+        "type": proxy.protocol,
+        "speed": proxy.time_to_connect.as_secs().to_string(),
+        "support": {
+            "https": flag(proxy.supports.https),
+            "get": flag(proxy.supports.get),
+            "post": flag(proxy.supports.post),
+            "cookies": flag(proxy.supports.cookies),
+            "referer": flag(proxy.supports.referer),
+            "user_agent": flag(proxy.supports.forwards_user_agent),
+            "google": flag(proxy.supports.connects_to_google),
+        },
+    })
+}
@@ -1,9 +1,16 @@
 //! [`Opts`][Opts] provide the ability to filter the returned proxies.
 
-use std::num::NonZeroU16;
+use std::{convert::TryFrom, env, num::NonZeroU16, time::Duration};
 
-use crate::types::{Countries, LastChecked, Level, Protocol, TimeToConnect};
+use crate::{
+    constants,
+    errors::{ApiKeyError, OptsError},
+    proxy::Proxy,
+    types::{join_codes, Countries, LastChecked, Level, Protocol, TimeToConnect},
+};
 
+use chrono::Utc;
+use iso_country::Country;
 use serde::Serialize;
 use serde_repr::Serialize_repr;
 
@@ -24,6 +31,7 @@ pub struct OptsBuilder {
     cookies: Option<bool>,
     connects_to_google: Option<bool>,
     https: Option<bool>,
+    get: Option<bool>,
     post: Option<bool>,
     referer: Option<bool>,
     forwards_user_agent: Option<bool>,
@@ -36,8 +44,37 @@ impl OptsBuilder {
         self
     }
 
-    /// The anonymity level of proxies returned by the API where the proxies are either Anonymous or
-    /// Elite (Transparent isn't provided).
+    /// Reads the API key from the `PUBPROXY_API_KEY` environment variable. This keeps the key out
+    /// of source while still composing with the rest of the builder. An error is returned if the
+    /// variable isn't set or is empty.
+    ///
+    /// ```no_run
+    /// use lead_oxide::{opts::Opts, types::Level};
+    ///
+    /// let opts = Opts::builder()
+    ///     .api_key_from_env()
+    ///     .unwrap()
+    ///     .level(Level::Elite)
+    ///     .build();
+    /// ```
+    pub fn api_key_from_env(self) -> Result<Self, ApiKeyError> {
+        self.api_key_from_var(constants::API_KEY_VAR)
+    }
+
+    /// Like [`api_key_from_env`][OptsBuilder::api_key_from_env], but reads the API key from the
+    /// environment variable called `name` instead.
+    pub fn api_key_from_var(self, name: &str) -> Result<Self, ApiKeyError> {
+        match env::var(name) {
+            Ok(api_key) if api_key.is_empty() => Err(ApiKeyError::Empty(name.to_string())),
+            Ok(api_key) => Ok(self.api_key(api_key)),
+            Err(env::VarError::NotPresent) => Err(ApiKeyError::Missing(name.to_string())),
+            Err(env::VarError::NotUnicode(_)) => Err(ApiKeyError::NotUnicode(name.to_string())),
+        }
+    }
+
+    /// The anonymity level of proxies returned by the API where the proxies are either Transparent,
+    /// Anonymous, or Elite. Transparent proxies pass along your IP address, so they're rarely what
+    /// you want.
     pub fn level(mut self, level: Level) -> Self {
         self.level = Some(level);
         self
@@ -55,6 +92,12 @@ impl OptsBuilder {
         self
     }
 
+    /// A shortcut for blocking all of the provided `countries`. This is the same as
+    /// `.countries(Countries::block().countries(countries))`.
+    pub fn exclude_countries(self, countries: &[Country]) -> Self {
+        self.countries(Countries::block().countries(countries))
+    }
+
     /// Time when the proxies were last checked. Resolution down to minutes with a valid range of
     /// 1 to 1,000 minutes.
     pub fn last_checked(mut self, last_checked: LastChecked) -> Self {
@@ -94,6 +137,12 @@ impl OptsBuilder {
         self
     }
 
+    /// If the proxy supports GET requests or not.
+    pub fn get(mut self, get: bool) -> Self {
+        self.get = Some(get);
+        self
+    }
+
     /// If the proxy supports POST requests or not.
     pub fn post(mut self, post: bool) -> Self {
         self.post = Some(post);
@@ -118,34 +167,45 @@ impl OptsBuilder {
     }
 }
 
+/// Named combinations of options for common use cases.
+///
+/// Used with [`Opts::preset()`][Opts::preset] which returns an [`OptsBuilder`][OptsBuilder] so any
+/// of the options can still be tweaked further.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    /// Anonymous HTTP proxies that support HTTPS and connected within 5 seconds.
+    FastAnonymousHttps,
+    /// SOCKS5 proxies from any country.
+    SocksAnyCountry,
+    /// Elite proxies that behave like a regular browser by supporting cookies, referers, and
+    /// connecting to google. The API doesn't say whether a proxy is residential so this is the
+    /// closest approximation.
+    ResidentialElite,
+}
+
+/// The most proxies the API returns per request without an API key.
+pub const FREE_LIMIT: u8 = 5;
+/// The most proxies the API returns per request with an API key.
+pub const PREMIUM_LIMIT: u8 = 20;
+
 /// Internal
-#[derive(Serialize_repr, Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Serialize_repr, Clone, Copy, Debug, Default, PartialEq, Eq)]
 #[repr(u8)]
 pub(crate) enum Limit {
-    Free = 5,
-    Premium = 20,
-}
-
-impl Default for Limit {
-    fn default() -> Self {
-        Self::Free
-    }
+    #[default]
+    Free = FREE_LIMIT,
+    Premium = PREMIUM_LIMIT,
 }
 
 /// Internal
-#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 enum Format {
     // Techically txt is also allowed, but this library only uses json
+    #[default]
     Json,
 }
 
-impl Default for Format {
-    fn default() -> Self {
-        Self::Json
-    }
-}
-
 /// A set of options to constrain the returned proxies.
 ///
 /// `Opts` represents all the filtering options that are passed on to the API by the corresponding
@@ -202,6 +262,7 @@ pub struct Opts {
     #[serde(rename = "google")]
     connects_to_google: Option<bool>,
     https: Option<bool>,
+    get: Option<bool>,
     post: Option<bool>,
     referer: Option<bool>,
     #[serde(rename = "user_agent")]
@@ -216,10 +277,260 @@ impl Opts {
         OptsBuilder::default()
     }
 
-    /// Internal
-    pub(crate) fn is_premium(&self) -> bool {
+    /// Constructs an [`OptsBuilder`][OptsBuilder] already set up for the given
+    /// [`Preset`][Preset].
+    ///
+    /// ```
+    /// use iso_country::Country;
+    /// use lead_oxide::{
+    ///     opts::{Opts, Preset},
+    ///     types::Countries,
+    /// };
+    ///
+    /// // Fast anonymous HTTPS proxies, but only from the US
+    /// let opts = Opts::preset(Preset::FastAnonymousHttps)
+    ///     .countries(Countries::allow().country(Country::US))
+    ///     .build();
+    /// ```
+    pub fn preset(preset: Preset) -> OptsBuilder {
+        match preset {
+            Preset::FastAnonymousHttps => Self::builder()
+                .level(Level::Anonymous)
+                .protocol(Protocol::Http)
+                .https(true)
+                .time_to_connect(
+                    TimeToConnect::try_from(Duration::from_secs(5))
+                        .expect("5 seconds is within bounds"),
+                ),
+            Preset::SocksAnyCountry => Self::builder().protocol(Protocol::Socks5),
+            Preset::ResidentialElite => Self::builder()
+                .level(Level::Elite)
+                .cookies(true)
+                .referer(true)
+                .connects_to_google(true),
+        }
+    }
+
+    /// If an API key is being used.
+    pub fn is_premium(&self) -> bool {
         self.api_key.is_some()
     }
+
+    /// The anonymity level filter if one is set.
+    pub fn level(&self) -> Option<Level> {
+        self.level
+    }
+
+    /// The protocol filter if one is set.
+    pub fn protocol(&self) -> Option<Protocol> {
+        self.protocol
+    }
+
+    /// The country filter if any countries are listed.
+    pub fn countries(&self) -> Option<&Countries> {
+        if self.countries.is_empty() {
+            None
+        } else {
+            Some(&self.countries)
+        }
+    }
+
+    /// The maximum number of proxies returned per request which depends on if an API key is used.
+    /// This is always picked automatically, so it's either [`FREE_LIMIT`][FREE_LIMIT] or
+    /// [`PREMIUM_LIMIT`][PREMIUM_LIMIT].
+    ///
+    /// ```
+    /// use lead_oxide::opts::{Opts, FREE_LIMIT, PREMIUM_LIMIT};
+    ///
+    /// assert_eq!(Opts::default().limit(), FREE_LIMIT);
+    /// let premium = Opts::builder().api_key("<key>".to_string()).build();
+    /// assert_eq!(premium.limit(), PREMIUM_LIMIT);
+    /// ```
+    pub fn limit(&self) -> u8 {
+        self.limit as u8
+    }
+
+    /// Lists the name and value of every option that is set in the same order as the methods on
+    /// [`OptsBuilder`][OptsBuilder]. The API key is always redacted. This is mainly intended for
+    /// displaying the current configuration.
+    ///
+    /// ```
+    /// use lead_oxide::{opts::Opts, types::Level};
+    ///
+    /// let opts = Opts::builder().level(Level::Elite).post(true).build();
+    /// assert_eq!(
+    ///     opts.describe(),
+    ///     vec![("level", "elite".to_string()), ("post", "true".to_string())]
+    /// );
+    /// ```
+    pub fn describe(&self) -> Vec<(&'static str, String)> {
+        let countries = self.countries().map(|countries| match countries {
+            Countries::AllowList(list) => format!("allow {}", join_codes(list)),
+            Countries::BlockList(list) => format!("block {}", join_codes(list)),
+        });
+
+        let options = vec![
+            (
+                "api_key",
+                self.api_key.as_ref().map(|_| "<redacted>".to_string()),
+            ),
+            ("level", self.level.as_ref().map(api_value)),
+            ("protocol", self.protocol.as_ref().map(api_value)),
+            ("countries", countries),
+            (
+                "last_checked",
+                self.last_checked
+                    .map(|minutes| format!("{} minutes", minutes)),
+            ),
+            ("port", self.port.map(|port| port.to_string())),
+            (
+                "time_to_connect",
+                self.time_to_connect.map(|secs| format!("{} seconds", secs)),
+            ),
+            ("cookies", self.cookies.map(|flag| flag.to_string())),
+            (
+                "connects_to_google",
+                self.connects_to_google.map(|flag| flag.to_string()),
+            ),
+            ("https", self.https.map(|flag| flag.to_string())),
+            ("get", self.get.map(|flag| flag.to_string())),
+            ("post", self.post.map(|flag| flag.to_string())),
+            ("referer", self.referer.map(|flag| flag.to_string())),
+            (
+                "forwards_user_agent",
+                self.forwards_user_agent.map(|flag| flag.to_string()),
+            ),
+        ];
+
+        options
+            .into_iter()
+            .filter_map(|(name, value)| value.map(|value| (name, value)))
+            .collect()
+    }
+
+    /// Internal
+    pub(crate) fn to_query_string(&self) -> Result<String, OptsError> {
+        query_string(self)
+    }
+
+    /// Internal
+    pub(crate) fn score(&self, proxy: &Proxy) -> usize {
+        // Each option that was specified and matches the proxy is worth a point
+        self.matches(proxy)
+            .iter()
+            .filter(|(_, matched)| *matched)
+            .count()
+    }
+
+    /// Internal
+    pub(crate) fn matches(&self, proxy: &Proxy) -> Vec<(&'static str, bool)> {
+        let supports = &proxy.supports;
+        let minutes_since_checked = Utc::now()
+            .naive_utc()
+            .signed_duration_since(proxy.last_checked)
+            .num_minutes();
+        let countries = if self.countries.is_empty() {
+            None
+        } else {
+            Some(self.countries.permits(proxy.country))
+        };
+        let wants = |wanted: Option<bool>, supported: bool| wanted.map(|flag| flag == supported);
+
+        // Uses the same names and order as `describe` while skipping unset options
+        let checks = vec![
+            ("level", self.level.map(|level| level == proxy.level)),
+            (
+                "protocol",
+                self.protocol.map(|protocol| protocol == proxy.protocol),
+            ),
+            ("countries", countries),
+            (
+                "last_checked",
+                self.last_checked
+                    .map(|minutes| minutes_since_checked <= minutes as i64),
+            ),
+            (
+                "port",
+                self.port.map(|port| port.get() == proxy.socket.port()),
+            ),
+            (
+                "time_to_connect",
+                self.time_to_connect
+                    .map(|secs| proxy.time_to_connect.as_secs() <= secs),
+            ),
+            ("cookies", wants(self.cookies, supports.cookies)),
+            (
+                "connects_to_google",
+                wants(self.connects_to_google, supports.connects_to_google),
+            ),
+            ("https", wants(self.https, supports.https)),
+            ("get", wants(self.get, supports.get)),
+            ("post", wants(self.post, supports.post)),
+            ("referer", wants(self.referer, supports.referer)),
+            (
+                "forwards_user_agent",
+                wants(self.forwards_user_agent, supports.forwards_user_agent),
+            ),
+        ];
+
+        checks
+            .into_iter()
+            .filter_map(|(name, matched)| matched.map(|matched| (name, matched)))
+            .collect()
+    }
+
+    /// Internal
+    pub(crate) fn allowed_countries(&self) -> Vec<Country> {
+        self.countries.allowed()
+    }
+
+    /// Internal
+    pub(crate) fn with_countries(&self, countries: Countries) -> Self {
+        Self {
+            countries,
+            ..self.clone()
+        }
+    }
+
+    /// Internal
+    pub(crate) fn redacted(&self) -> Self {
+        Self {
+            api_key: self.api_key.as_ref().map(|_| "<redacted>".to_string()),
+            ..self.clone()
+        }
+    }
+}
+
+// Uses the same representation as the API for enums
+fn api_value<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(String::from))
+        .unwrap_or_default()
+}
+
+// Values are percent-encoded by `serde_urlencoded`, so the only failures are from values that
+// can't be represented in a url at all. In that case each field is tried on its own to find the one
+// responsible
+fn query_string<T: Serialize>(value: &T) -> Result<String, OptsError> {
+    serde_urlencoded::to_string(value).map_err(|err| {
+        let field = serde_json::to_value(value)
+            .ok()
+            .and_then(|value| {
+                value.as_object().and_then(|fields| {
+                    fields
+                        .iter()
+                        .find(|field| serde_urlencoded::to_string([field]).is_err())
+                        .map(|(name, _)| name.clone())
+                })
+            })
+            .unwrap_or_else(|| "unknown".to_string());
+
+        OptsError::Unrepresentable {
+            field,
+            reason: err.to_string(),
+        }
+    })
 }
 
 impl From<OptsBuilder> for Opts {
@@ -243,6 +554,7 @@ impl From<OptsBuilder> for Opts {
             cookies: builder.cookies,
             connects_to_google: builder.connects_to_google,
             https: builder.https,
+            get: builder.get,
             post: builder.post,
             referer: builder.referer,
             forwards_user_agent: builder.forwards_user_agent,
@@ -255,27 +567,26 @@ impl From<OptsBuilder> for Opts {
 mod tests {
     use super::*;
 
-    use std::{convert::TryFrom, time::Duration};
-
-    use iso_country::Country;
-
-    #[test]
-    fn url_serialization() -> Result<(), serde_urlencoded::ser::Error> {
-        let check_equivalent_params = |opts, expected: &[&str]| {
-            // Convert `opts` to a url and sort the values
-            let url = serde_urlencoded::to_string(&opts)?;
-            let mut params: Vec<_> = url.split('&').map(String::from).collect();
-            params.sort();
+    fn check_equivalent_params(
+        opts: Opts,
+        expected: &[&str],
+    ) -> Result<(), serde_urlencoded::ser::Error> {
+        // Convert `opts` to a url and sort the values
+        let url = serde_urlencoded::to_string(&opts)?;
+        let mut params: Vec<_> = url.split('&').map(String::from).collect();
+        params.sort();
 
-            // Sort the `expected` values
-            let mut expected = expected.to_vec();
-            expected.sort_unstable();
+        // Sort the `expected` values
+        let mut expected = expected.to_vec();
+        expected.sort_unstable();
 
-            assert_eq!(params, expected);
+        assert_eq!(params, expected);
 
-            Ok(())
-        };
+        Ok(())
+    }
 
+    #[test]
+    fn url_serialization() -> Result<(), serde_urlencoded::ser::Error> {
         // Base `Opts`
         check_equivalent_params(Opts::default(), &["format=json", "limit=5"])?;
         // Using a key will up the limit
@@ -289,6 +600,18 @@ mod tests {
             Opts::builder().countries(Countries::default()).build(),
             &["format=json", "limit=5"],
         )?;
+        // Transparent proxies can be requested too
+        check_equivalent_params(
+            Opts::builder().level(Level::Transparent).build(),
+            &["format=json", "level=transparent", "limit=5"],
+        )?;
+        // Excluding countries is just a blocklist
+        check_equivalent_params(
+            Opts::builder()
+                .exclude_countries(&[Country::CH, Country::ES])
+                .build(),
+            &["format=json", "limit=5", "not_country=CH%2CES"],
+        )?;
         // Kitchen sink
         check_equivalent_params(
             Opts::builder()
@@ -331,4 +654,188 @@ mod tests {
             ],
         )
     }
+
+    #[test]
+    fn presets() -> Result<(), serde_urlencoded::ser::Error> {
+        check_equivalent_params(
+            Opts::preset(Preset::FastAnonymousHttps).build(),
+            &[
+                "format=json",
+                "limit=5",
+                "level=anonymous",
+                "type=http",
+                "https=true",
+                "speed=5",
+            ],
+        )?;
+        check_equivalent_params(
+            Opts::preset(Preset::SocksAnyCountry).build(),
+            &["format=json", "limit=5", "type=socks5"],
+        )?;
+        check_equivalent_params(
+            Opts::preset(Preset::ResidentialElite).build(),
+            &[
+                "format=json",
+                "limit=5",
+                "level=elite",
+                "cookies=true",
+                "referer=true",
+                "google=true",
+            ],
+        )?;
+        // Presets can still be tweaked further
+        check_equivalent_params(
+            Opts::preset(Preset::SocksAnyCountry)
+                .protocol(Protocol::Socks4)
+                .post(true)
+                .build(),
+            &["format=json", "limit=5", "type=socks4", "post=true"],
+        )?;
+        // Either request method can be filtered on
+        check_equivalent_params(
+            Opts::builder().get(true).post(true).build(),
+            &["format=json", "limit=5", "get=true", "post=true"],
+        )
+    }
+
+    #[test]
+    fn scoring() {
+        let proxy = Proxy::builder()
+            .socket("1.2.3.4:8080".parse().unwrap())
+            .country(Country::US)
+            .level(Level::Elite)
+            .protocol(Protocol::Http)
+            .time_to_connect(Duration::from_secs(3))
+            .build();
+
+        // Nothing specified means nothing to match
+        assert_eq!(Opts::default().score(&proxy), 0);
+
+        let opts = Opts::builder()
+            .level(Level::Elite)
+            .protocol(Protocol::Socks5)
+            .last_checked(LastChecked::try_from(Duration::from_secs(60)).unwrap())
+            .port(NonZeroU16::new(8080).unwrap())
+            .time_to_connect(TimeToConnect::try_from(Duration::from_secs(2)).unwrap())
+            .https(false)
+            .cookies(true)
+            .build();
+        // Level, last checked, port, and https all match
+        assert_eq!(opts.score(&proxy), 4);
+
+        let in_countries = |countries| Opts::builder().countries(countries).build().score(&proxy);
+        assert_eq!(in_countries(Countries::allow().country(Country::US)), 1);
+        assert_eq!(in_countries(Countries::allow().country(Country::CA)), 0);
+        assert_eq!(in_countries(Countries::block().country(Country::CA)), 1);
+        assert_eq!(in_countries(Countries::block().country(Country::US)), 0);
+    }
+
+    #[test]
+    fn query_string_encoding() {
+        // Reserved and non-ascii characters are percent-encoded
+        let opts = Opts::builder().api_key("a&b=c d/?é".to_string()).build();
+        let query = opts.to_query_string().unwrap();
+        assert!(
+            query.contains("api=a%26b%3Dc+d%2F%3F%C3%A9"),
+            "Bad encoding: {}",
+            query
+        );
+
+        // Values that can't be represented at all name the offending field
+        #[derive(Serialize)]
+        struct Unrepresentable {
+            fine: bool,
+            nested: Vec<u8>,
+        }
+        match query_string(&Unrepresentable {
+            fine: true,
+            nested: vec![1, 2],
+        }) {
+            Err(OptsError::Unrepresentable { field, .. }) => assert_eq!(field, "nested"),
+            other => panic!("Expected `Unrepresentable`, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn introspection() {
+        let basic = Opts::default();
+        assert!(!basic.is_premium());
+        assert_eq!(basic.level(), None);
+        assert_eq!(basic.protocol(), None);
+        assert_eq!(basic.countries(), None);
+        assert_eq!(basic.limit(), 5);
+        assert!(basic.describe().is_empty());
+
+        let kitchen_sink = Opts::builder()
+            .api_key("<key>".to_string())
+            .level(Level::Elite)
+            .protocol(Protocol::Socks4)
+            .countries(Countries::block().countries(&[Country::CH, Country::ES]))
+            .last_checked(LastChecked::try_from(Duration::from_secs(60 * 10)).unwrap())
+            .time_to_connect(TimeToConnect::try_from(Duration::from_secs(10)).unwrap())
+            .port(NonZeroU16::new(8080).unwrap())
+            .cookies(true)
+            .connects_to_google(false)
+            .https(true)
+            .post(false)
+            .referer(true)
+            .forwards_user_agent(false)
+            .build();
+        assert!(kitchen_sink.is_premium());
+        assert_eq!(kitchen_sink.level(), Some(Level::Elite));
+        assert_eq!(kitchen_sink.protocol(), Some(Protocol::Socks4));
+        assert_eq!(
+            kitchen_sink.countries(),
+            Some(&Countries::block().countries(&[Country::CH, Country::ES]))
+        );
+        assert_eq!(kitchen_sink.limit(), 20);
+
+        let described: Vec<_> = kitchen_sink
+            .describe()
+            .into_iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        assert_eq!(
+            described,
+            [
+                "api_key=<redacted>",
+                "level=elite",
+                "protocol=socks4",
+                "countries=block CH,ES",
+                "last_checked=10 minutes",
+                "port=8080",
+                "time_to_connect=10 seconds",
+                "cookies=true",
+                "connects_to_google=false",
+                "https=true",
+                "post=false",
+                "referer=true",
+                "forwards_user_agent=false",
+            ]
+        );
+    }
+
+    #[test]
+    fn api_key_from_env() {
+        // Each case uses its own variable since the environment is shared between tests
+        env::set_var("LEAD_OXIDE_TEST_KEY", "<key>");
+        let opts = Opts::builder()
+            .api_key_from_var("LEAD_OXIDE_TEST_KEY")
+            .unwrap()
+            .level(Level::Elite)
+            .build();
+        assert!(opts.is_premium());
+        assert_eq!(opts.api_key.as_deref(), Some("<key>"));
+
+        env::set_var("LEAD_OXIDE_EMPTY_KEY", "");
+        assert_eq!(
+            Opts::builder().api_key_from_var("LEAD_OXIDE_EMPTY_KEY"),
+            Err(ApiKeyError::Empty("LEAD_OXIDE_EMPTY_KEY".to_string()))
+        );
+
+        assert_eq!(
+            Opts::builder().api_key_from_var("LEAD_OXIDE_MISSING_KEY"),
+            Err(ApiKeyError::Missing("LEAD_OXIDE_MISSING_KEY".to_string()))
+        );
+    }
 }
@@ -0,0 +1,249 @@
+//! [`FetchPolicy`][FetchPolicy] bundles up how a [`Fetcher`][crate::fetcher::Fetcher] goes about
+//! requesting the API.
+
+use std::time::Duration;
+
+use crate::constants;
+
+/// A builder for setting up a [`FetchPolicy`][FetchPolicy].
+///
+/// Constructed with `FetchPolicy::builder()`. Any knob that isn't set keeps the current default
+/// behavior.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FetchPolicyBuilder {
+    policy: FetchPolicy,
+}
+
+impl FetchPolicyBuilder {
+    /// Limits how long a single request to the API can take before returning an
+    /// [`ApiError::Timeout`][crate::errors::ApiError::Timeout]. By default there is no timeout.
+    pub fn request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.policy.request_timeout = Some(request_timeout);
+        self
+    }
+
+    /// The minimum time between keyless requests from the same
+    /// [`Session`][crate::session::Session]. This defaults to just over a second which keeps
+    /// within the API's rate-limit, so going any lower is likely to get you rate-limited.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.policy.delay = delay;
+        self
+    }
+
+    /// How many requests are made when searching for specific proxies before giving up with an
+    /// [`ApiError::AttemptsExhausted`][crate::errors::ApiError::AttemptsExhausted]. This defaults
+    /// to 5.
+    pub fn max_attempts(mut self, max_attempts: usize) -> Self {
+        self.policy.max_attempts = max_attempts;
+        self
+    }
+
+    /// How many times a `Retry-After` from the API is waited out for a single request before the
+    /// rate-limiting is returned as an error. This defaults to 3.
+    pub fn max_retry_after(mut self, max_retry_after: usize) -> Self {
+        self.policy.max_retry_after = max_retry_after;
+        self
+    }
+
+    /// How many times a request that failed with a
+    /// [transient error][crate::errors::ApiError::is_transient] is retried before the error is
+    /// returned. Other errors are never retried. This defaults to 0.
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.policy.max_retries = max_retries;
+        self
+    }
+
+    /// How long to wait before the first retry of a transient error, doubling with each retry
+    /// after that. Keyless requests always wait at least the usual
+    /// [`delay`][FetchPolicyBuilder::delay] too. This defaults to 500 milliseconds.
+    pub fn retry_backoff(mut self, retry_backoff: Duration) -> Self {
+        self.policy.retry_backoff = retry_backoff;
+        self
+    }
+
+    /// The maximum size of a response body that will be read from the API. This defaults to 1 MiB.
+    pub fn max_response_bytes(mut self, max_response_bytes: usize) -> Self {
+        self.policy.max_response_bytes = max_response_bytes;
+        self
+    }
+
+    /// Constructs the `FetchPolicyBuilder` into the corresponding [`FetchPolicy`][FetchPolicy]
+    /// value.
+    pub fn build(self) -> FetchPolicy {
+        self.policy
+    }
+}
+
+/// The knobs that control how requests to the API are made.
+///
+/// A `FetchPolicy` can be set for a whole [`Session`][crate::session::Session] with
+/// [`Session::with_policy`][crate::session::Session::with_policy] which is then used by every
+/// `Fetcher` it creates, or for a single `Fetcher` with
+/// [`Fetcher::with_policy`][crate::fetcher::Fetcher::with_policy]. `FetchPolicy::default()`
+/// matches the usual behavior.
+///
+/// ```
+/// use lead_oxide::{opts::Opts, policy::FetchPolicy, session::Session};
+/// use std::time::Duration;
+///
+/// let patient = FetchPolicy::builder()
+///     .request_timeout(Duration::from_secs(30))
+///     .delay(Duration::from_secs(2))
+///     .max_attempts(10)
+///     .build();
+/// let session = Session::new().with_policy(patient);
+/// let fetcher = session.fetcher(Opts::default());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct FetchPolicy {
+    pub(crate) request_timeout: Option<Duration>,
+    pub(crate) delay: Duration,
+    pub(crate) max_attempts: usize,
+    pub(crate) max_retry_after: usize,
+    pub(crate) max_retries: usize,
+    pub(crate) retry_backoff: Duration,
+    pub(crate) max_response_bytes: usize,
+}
+
+impl FetchPolicy {
+    /// Constructs a [`FetchPolicyBuilder`][FetchPolicyBuilder]
+    pub fn builder() -> FetchPolicyBuilder {
+        FetchPolicyBuilder::default()
+    }
+}
+
+impl Default for FetchPolicy {
+    fn default() -> Self {
+        Self {
+            request_timeout: None,
+            delay: constants::DELAY,
+            max_attempts: constants::MAX_ATTEMPTS,
+            max_retry_after: constants::MAX_RETRY_AFTER,
+            max_retries: constants::MAX_RETRIES,
+            retry_backoff: constants::RETRY_BACKOFF,
+            max_response_bytes: constants::MAX_RESPONSE_BYTES,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{collections::HashSet, time::Instant};
+
+    use crate::{errors::ApiError, mock, opts::Opts, session::Session};
+
+    #[test]
+    fn builder() {
+        assert_eq!(FetchPolicy::builder().build(), FetchPolicy::default());
+
+        let policy = FetchPolicy::builder()
+            .request_timeout(Duration::from_secs(1))
+            .delay(Duration::from_secs(2))
+            .max_attempts(3)
+            .max_retry_after(4)
+            .max_retries(5)
+            .retry_backoff(Duration::from_secs(6))
+            .max_response_bytes(7)
+            .build();
+        assert_eq!(
+            policy,
+            FetchPolicy {
+                request_timeout: Some(Duration::from_secs(1)),
+                delay: Duration::from_secs(2),
+                max_attempts: 3,
+                max_retry_after: 4,
+                max_retries: 5,
+                retry_backoff: Duration::from_secs(6),
+                max_response_bytes: 7,
+            }
+        );
+    }
+
+    #[test]
+    fn knobs() {
+        let delay = constants::DELAY * 3;
+        let policy = FetchPolicy::builder()
+            .request_timeout(Duration::from_secs(1))
+            .delay(delay)
+            .max_attempts(2)
+            .max_retry_after(0)
+            .build();
+        let session = Session::new().with_policy(policy.clone());
+        let mut fetcher = session.fetcher(Opts::default());
+        assert_eq!(fetcher.policy(), &policy);
+
+        // The delay is used between requests
+        let start = Instant::now();
+        fetcher.fetch_page().unwrap();
+        fetcher.fetch_page().unwrap();
+        assert!(start.elapsed() >= delay, "{:?}", start.elapsed());
+
+        // Searching gives up after `max_attempts`
+        let in_use: HashSet<_> = (2..4)
+            .flat_map(|nth| mock::nth_page(nth, 5))
+            .map(|proxy| proxy.socket)
+            .collect();
+        assert!(matches!(
+            fetcher.try_get_excluding(1, &in_use),
+            Err(ApiError::AttemptsExhausted { attempts: 2 })
+        ));
+        assert_eq!(session.requests_today(), 4);
+
+        // And `Retry-After` isn't waited out at all
+        mock::push_retry_after(Duration::from_millis(10));
+        assert!(matches!(fetcher.fetch_page(), Err(ApiError::RateLimit)));
+
+        // An individual fetcher can still use its own policy
+        let fetcher = session
+            .fetcher(Opts::default())
+            .with_policy(FetchPolicy::default());
+        assert_eq!(fetcher.policy(), &FetchPolicy::default());
+    }
+
+    #[test]
+    fn retries() {
+        let backoff = Duration::from_millis(10);
+        let policy = FetchPolicy::builder()
+            .max_retries(2)
+            .retry_backoff(backoff)
+            .build();
+        let session = Session::new().with_policy(policy);
+        let opts = Opts::builder().api_key("<key>".to_string()).build();
+        let mut fetcher = session.fetcher(opts);
+
+        // Transient errors are retried with a growing backoff
+        mock::push_response(500, "Internal Server Error");
+        mock::push_response(503, "Service Unavailable");
+        let start = Instant::now();
+        assert!(!fetcher.fetch_page().unwrap().proxies.is_empty());
+        assert!(start.elapsed() >= backoff * 3, "{:?}", start.elapsed());
+        assert_eq!(session.requests_today(), 3);
+
+        // until they run out
+        for _ in 0..3 {
+            mock::push_response(500, "Internal Server Error");
+        }
+        assert!(matches!(
+            fetcher.fetch_page(),
+            Err(ApiError::Server { status: 500, .. })
+        ));
+        assert_eq!(session.requests_today(), 6);
+
+        // Other errors are returned right away
+        mock::push_response(400, "Bad Request");
+        assert!(matches!(
+            fetcher.fetch_page(),
+            Err(ApiError::Client { status: 400, .. })
+        ));
+        assert_eq!(session.requests_today(), 7);
+
+        // Keyless retries still wait out the usual delay
+        let mut keyless = session.fetcher(Opts::default());
+        mock::push_response(500, "Internal Server Error");
+        let start = Instant::now();
+        keyless.fetch_page().unwrap();
+        assert!(start.elapsed() >= constants::DELAY, "{:?}", start.elapsed());
+    }
+}
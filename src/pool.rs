@@ -0,0 +1,347 @@
+//! [`ProxyPool`][ProxyPool] keeps a minimum number of fresh proxies on hand for long-running
+//! programs.
+
+use std::{
+    collections::VecDeque,
+    num::NonZeroUsize,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    constants,
+    errors::ApiError,
+    fetcher::{checked_within, Fetcher},
+    proxy::Proxy,
+};
+
+/// Internal
+#[derive(Debug)]
+struct State {
+    proxies: VecDeque<Proxy>,
+    running: bool,
+    max_age: Option<Duration>,
+}
+
+impl State {
+    fn is_fresh(&self, proxy: &Proxy) -> bool {
+        self.max_age
+            .is_none_or(|max_age| checked_within(proxy, max_age))
+    }
+
+    // Adds any of `proxies` that are still fresh and aren't already in the pool
+    fn extend(&mut self, proxies: Vec<Proxy>) {
+        for proxy in proxies {
+            let duplicate = self
+                .proxies
+                .iter()
+                .any(|pooled| pooled.socket == proxy.socket);
+            if !duplicate && self.is_fresh(&proxy) {
+                self.proxies.push_back(proxy);
+            }
+        }
+    }
+
+    fn drop_stale(&mut self) {
+        if let Some(max_age) = self.max_age {
+            self.proxies.retain(|proxy| checked_within(proxy, max_age));
+        }
+    }
+}
+
+/// Internal
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // The state is always left valid so a poisoned lock is fine to use
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+type Worker = JoinHandle<(Fetcher, Result<(), ApiError>)>;
+
+/// A pool that keeps at least a minimum number of fresh proxies available.
+///
+/// Once started a `ProxyPool` refills itself with its [`Fetcher`][crate::fetcher::Fetcher] on a
+/// background thread whenever proxies are [`acquire`][ProxyPool::acquire]d or go stale. Proxies
+/// that are still good can be handed back with [`release`][ProxyPool::release]. All fetching
+/// goes through the `Fetcher`'s [`Session`][crate::session::Session], so the pool shares the
+/// usual delays with every other `Fetcher` from that `Session`.
+///
+/// ```no_run
+/// use lead_oxide::{fetcher::Fetcher, pool::ProxyPool};
+/// use std::{num::NonZeroUsize, time::Duration};
+///
+/// let mut pool = ProxyPool::new(Fetcher::default(), NonZeroUsize::new(10).unwrap())
+///     .with_max_age(Duration::from_secs(30 * 60));
+/// pool.start();
+/// if let Some(proxy) = pool.acquire() {
+///     // Use the proxy, and hand it back if it worked out
+///     pool.release(proxy);
+/// }
+/// pool.stop().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ProxyPool {
+    fetcher: Option<Fetcher>,
+    shared: Arc<Shared>,
+    min: usize,
+    interval: Duration,
+    worker: Option<Worker>,
+}
+
+impl ProxyPool {
+    /// Creates a stopped `ProxyPool` that fetches with `fetcher` to keep at least `min` proxies
+    /// available.
+    pub fn new(fetcher: Fetcher, min: NonZeroUsize) -> Self {
+        Self {
+            fetcher: Some(fetcher),
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    proxies: VecDeque::with_capacity(min.get()),
+                    running: false,
+                    max_age: None,
+                }),
+                changed: Condvar::new(),
+            }),
+            min: min.get(),
+            interval: constants::DELAY,
+            worker: None,
+        }
+    }
+
+    /// Sets how long after their `last_checked` proxies are considered stale. Stale proxies are
+    /// dropped from the pool and replaced with fresh ones. By default proxies never go stale.
+    pub fn with_max_age(self, max_age: Duration) -> Self {
+        self.shared.lock().max_age = Some(max_age);
+        self
+    }
+
+    /// Sets how often the pool is checked for stale proxies while it's full. This defaults to the
+    /// same delay that is used to avoid rate-limiting on the keyless API. This takes effect the
+    /// next time the pool is started.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Starts refilling in the background. This does nothing if the pool is already running. If a
+    /// previous run stopped from an error that wasn't returned by [`stop`][ProxyPool::stop] then
+    /// that error is discarded.
+    pub fn start(&mut self) {
+        if self.is_running() {
+            return;
+        }
+
+        let _ = self.join();
+        let mut fetcher = self
+            .fetcher
+            .take()
+            .expect("The fetcher is always returned when the pool stops");
+        let shared = Arc::clone(&self.shared);
+        let min = self.min;
+        let interval = self.interval;
+
+        self.shared.lock().running = true;
+        self.worker = Some(thread::spawn(move || loop {
+            let needed = {
+                let mut state = shared.lock();
+                state.drop_stale();
+                while state.running && state.proxies.len() >= min {
+                    state = shared
+                        .changed
+                        .wait_timeout(state, interval)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .0;
+                    state.drop_stale();
+                }
+
+                if !state.running {
+                    return (fetcher, Ok(()));
+                }
+
+                min - state.proxies.len()
+            };
+
+            match fetcher.try_get(needed) {
+                Ok(mut proxies) => {
+                    // Everything left over from the page is worth keeping too
+                    proxies.append(&mut fetcher.take_buffered());
+                    shared.lock().extend(proxies);
+                    shared.changed.notify_all();
+                }
+                Err(err) => {
+                    shared.lock().running = false;
+                    shared.changed.notify_all();
+                    return (fetcher, Err(err));
+                }
+            }
+        }));
+    }
+
+    /// Stops refilling in the background, blocking until any in-progress fetch finishes. If the
+    /// pool stopped on its own because the API returned an error then that error is returned here.
+    /// Any proxies still in the pool can still be acquired.
+    pub fn stop(&mut self) -> Result<(), ApiError> {
+        self.shared.lock().running = false;
+        self.shared.changed.notify_all();
+        self.join()
+    }
+
+    /// If the pool is currently refilling in the background.
+    pub fn is_running(&self) -> bool {
+        self.shared.lock().running
+    }
+
+    /// The number of fresh proxies currently in the pool.
+    pub fn available(&self) -> usize {
+        let mut state = self.shared.lock();
+        state.drop_stale();
+        state.proxies.len()
+    }
+
+    /// Takes a fresh proxy from the pool, blocking until one is available. `None` is returned once
+    /// the pool is empty and no longer running, either from being stopped or from the API
+    /// returning an error.
+    pub fn acquire(&self) -> Option<Proxy> {
+        let mut state = self.shared.lock();
+        loop {
+            state.drop_stale();
+            if let Some(proxy) = state.proxies.pop_front() {
+                // Let the worker know that it's time to refill
+                self.shared.changed.notify_all();
+                return Some(proxy);
+            }
+
+            if !state.running {
+                return None;
+            }
+
+            state = self
+                .shared
+                .changed
+                .wait(state)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+
+    /// Hands a proxy back to the pool so that it can be acquired again. Proxies that have gone
+    /// stale or that are already in the pool are dropped instead.
+    pub fn release(&self, proxy: Proxy) {
+        self.shared.lock().extend(vec![proxy]);
+        self.shared.changed.notify_all();
+    }
+
+    fn join(&mut self) -> Result<(), ApiError> {
+        match self.worker.take() {
+            Some(worker) => {
+                let (fetcher, result) = worker.join().expect("The background refill loop panicked");
+                self.fetcher = Some(fetcher);
+                result
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for ProxyPool {
+    fn drop(&mut self) {
+        self.shared.lock().running = false;
+        self.shared.changed.notify_all();
+        if let Some(worker) = self.worker.take() {
+            // Don't panic from a panicked worker while dropping
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Instant;
+
+    use crate::{mock, opts::Opts, session::Session};
+
+    use chrono::Utc;
+
+    fn min(min: usize) -> NonZeroUsize {
+        NonZeroUsize::new(min).unwrap()
+    }
+
+    fn wait_for(pool: &ProxyPool, available: usize) {
+        let start = Instant::now();
+        while pool.available() < available {
+            assert!(start.elapsed() < Duration::from_secs(5), "The pool stalled");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn keeps_minimum() {
+        let session = Session::new();
+        let mut pool = ProxyPool::new(session.fetcher(Opts::default()), min(3))
+            .with_interval(Duration::from_secs(0));
+        assert!(!pool.is_running());
+        assert_eq!(pool.available(), 0);
+
+        // A full page gets pooled even though only 3 were needed
+        pool.start();
+        assert!(pool.is_running());
+        let proxy = pool.acquire().unwrap();
+        wait_for(&pool, 4);
+        assert_eq!(session.requests_today(), 1);
+
+        // Releasing hands the proxy back, but only once
+        pool.release(proxy.clone());
+        pool.release(proxy);
+        assert_eq!(pool.available(), 5);
+
+        // Draining the pool triggers a refill
+        for _ in 0..5 {
+            pool.acquire().unwrap();
+        }
+        wait_for(&pool, 3);
+        assert!(session.requests_today() >= 2);
+
+        pool.stop().unwrap();
+        assert!(!pool.is_running());
+    }
+
+    #[test]
+    fn drops_stale() {
+        let session = Session::new().with_daily_limit(2);
+        let mut pool = ProxyPool::new(session.fetcher(Opts::default()), min(1))
+            .with_max_age(Duration::from_secs(60 * 60))
+            .with_interval(Duration::from_secs(0));
+
+        // The default proxies were checked ages ago, so the pool never fills up before hitting
+        // the daily limit
+        pool.start();
+        assert_eq!(pool.acquire(), None);
+        assert!(!pool.is_running());
+
+        // Stale proxies can't be released back either
+        pool.release(mock::default_proxy());
+        assert_eq!(pool.available(), 0);
+        let fresh = Proxy {
+            last_checked: Utc::now().naive_utc(),
+            ..mock::default_proxy()
+        };
+        pool.release(fresh.clone());
+        assert_eq!(pool.acquire(), Some(fresh));
+
+        assert!(matches!(
+            pool.stop(),
+            Err(ApiError::DailyLimitReached { limit: 2 })
+        ));
+    }
+}
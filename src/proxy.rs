@@ -1,16 +1,28 @@
 //! [`Proxy`][Proxy]s represent information about the proxies returned by
 //! [`Fetcher`][crate::fetcher::Fetcher].
 
-use std::{net::SocketAddrV4, time::Duration};
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    convert::TryFrom,
+    fmt,
+    net::{Ipv4Addr, SocketAddr, TcpStream},
+    time::Duration,
+};
 
+#[cfg(any(feature = "hyper", feature = "reqwest"))]
+use crate::errors::UnsupportedProtocol;
 use crate::{
     constants::REPO_URI,
-    types::{Level, Protocol},
+    types::{LastChecked, Level, Protocol, TimeToConnect},
 };
 
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, Utc};
 use iso_country::Country;
-use serde::{de::Deserializer, Deserialize};
+use serde::{
+    de::{self, Deserializer},
+    Deserialize, Serialize,
+};
 
 /// Internal
 #[derive(Deserialize, Clone, Debug, PartialEq)]
@@ -22,7 +34,7 @@ struct Response {
 #[derive(Deserialize, Clone, Debug, PartialEq)]
 struct RawProxy {
     #[serde(rename = "ipPort")]
-    socket: SocketAddrV4,
+    socket: SocketAddr,
     #[serde(deserialize_with = "ignore_bad_countries")]
     country: Country,
     last_checked: String,
@@ -36,7 +48,8 @@ struct RawProxy {
     supports: RawSupports,
 }
 
-// Sometimes country codes other than iso 3166-1 are returned so switch those to unspecified
+// Sometimes country codes other than iso 3166-1 are returned (like "EU" or an empty string) so
+// switch those to unspecified instead of failing to parse the whole response
 /// Internal
 fn ignore_bad_countries<'de, D>(deserializer: D) -> Result<Country, D::Error>
 where
@@ -63,9 +76,21 @@ struct RawSupports {
 ///
 /// Typically most people will likely only use the `socket` value, but this contains all the
 /// information on a proxy.
-#[derive(Clone, Debug, PartialEq)]
+///
+/// The API occasionally lists proxies with a country that isn't a valid ISO 3166-1 code. These are
+/// parsed as `Country::Unspecified` and then left out of the proxies returned by a
+/// [`Fetcher`][crate::fetcher::Fetcher], so one odd entry doesn't cause the rest of the response to
+/// be lost.
+///
+/// Proxies are ordered by how fast they are rather than by their address, so sorting puts the
+/// fastest proxies first. See the [`Ord`][Proxy#impl-Ord-for-Proxy] implementation for the details.
+///
+/// Proxies can be serialized with `serde` for storing or forwarding them. This uses the field names
+/// of `Proxy` instead of the API's response format since the API's format is lossy (like `speed`
+/// being a string), so anything serialized can be deserialized back into the same `Proxy`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct Proxy {
-    pub socket: SocketAddrV4,
+    pub socket: SocketAddr,
     pub country: Country,
     pub last_checked: NaiveDateTime,
     pub level: Level,
@@ -74,25 +99,354 @@ pub struct Proxy {
     pub supports: Supports,
 }
 
-impl From<RawProxy> for Proxy {
-    fn from(raw: RawProxy) -> Self {
-        let last_checked = NaiveDateTime::parse_from_str(&raw.last_checked, "%F %T")
-            .unwrap_or_else(|_| {
-                panic!(
-                    "The API returned an invalid time. Please raise an issue to address this at {}",
-                    REPO_URI
-                )
-            });
-
-        let secs_to_connect = raw.time_to_connect.parse().unwrap_or_else(|_| {
-            panic!(
-                "The API returned an invalid int. Please raise an issue to address this at {}",
-                REPO_URI
-            )
-        });
+impl Proxy {
+    /// Constructs a [`ProxyBuilder`][ProxyBuilder]
+    pub fn builder() -> ProxyBuilder {
+        ProxyBuilder::default()
+    }
+
+    /// Rates the proxy from `0.0` to `1.0` using the default [`QualityWeights`][QualityWeights].
+    /// See [`quality_score_with`][Proxy::quality_score_with] for how the score is calculated.
+    pub fn quality_score(&self) -> f64 {
+        self.quality_score_with(&QualityWeights::default())
+    }
+
+    /// Rates the proxy from `0.0` to `1.0` by combining several of its attributes into a single
+    /// number that can be ranked by. Each attribute is first scored from `0.0` to `1.0`:
+    ///
+    /// - `speed`: `1 - time_to_connect / 60s`, hitting `0.0` at the slowest the API allows
+    /// - `freshness`: `1 - time_since_last_checked / 60m`, hitting `0.0` at the oldest the API
+    ///   allows
+    /// - `level`: `1.0` for elite proxies, `0.5` for anonymous proxies, and `0.0` for transparent
+    ///   proxies
+    /// - `supports`: the fraction of the [`Supports`][Supports] fields that are supported
+    ///
+    /// The final score is the weighted average of these using `weights`.
+    ///
+    /// ```
+    /// use lead_oxide::proxy::{Proxy, QualityWeights};
+    /// use std::time::Duration;
+    ///
+    /// let proxy = Proxy::builder()
+    ///     .socket("1.2.3.4:1234".parse().unwrap())
+    ///     .time_to_connect(Duration::from_secs(15))
+    ///     .build();
+    /// // Only care about how quickly it connects
+    /// let speed_only = QualityWeights {
+    ///     speed: 1.0,
+    ///     freshness: 0.0,
+    ///     level: 0.0,
+    ///     supports: 0.0,
+    /// };
+    /// assert_eq!(proxy.quality_score_with(&speed_only), 0.75);
+    /// ```
+    pub fn quality_score_with(&self, weights: &QualityWeights) -> f64 {
+        self.quality_score_at(weights, Utc::now().naive_utc())
+    }
+
+    /// Internal
+    pub(crate) fn quality_score_at(&self, weights: &QualityWeights, now: NaiveDateTime) -> f64 {
+        // Both maximums are the least strict values the API accepts
+        let fraction_of =
+            |value: Duration, max: Duration| value.min(max).as_secs_f64() / max.as_secs_f64();
+
+        let speed = 1.0 - fraction_of(self.time_to_connect, TimeToConnect::BOUNDS.1);
+        // Anything checked in the "future" is treated as just checked
+        let age = (now - self.last_checked)
+            .to_std()
+            .unwrap_or_else(|_| Duration::from_secs(0));
+        let freshness = 1.0 - fraction_of(age, LastChecked::BOUNDS.1);
+        let level = match self.level {
+            Level::Elite => 1.0,
+            Level::Anonymous => 0.5,
+            Level::Transparent => 0.0,
+        };
+        let flags = self.supports.flags();
+        let supports =
+            flags.iter().filter(|&&supported| supported).count() as f64 / flags.len() as f64;
+
+        let total = weights.speed + weights.freshness + weights.level + weights.supports;
+        if total <= 0.0 {
+            return 0.0;
+        }
+
+        (weights.speed * speed
+            + weights.freshness * freshness
+            + weights.level * level
+            + weights.supports * supports)
+            / total
+    }
+
+    /// Checks if the proxy is accepting connections by trying to open a TCP connection to its
+    /// `socket` within `timeout`. Note that this does real network I/O and blocks for up to
+    /// `timeout`. It also only checks that something is listening, not that it'll actually proxy
+    /// your requests.
+    ///
+    /// ```no_run
+    /// use lead_oxide::fetcher::Fetcher;
+    /// use std::time::Duration;
+    ///
+    /// let mut fetcher = Fetcher::default();
+    /// let proxy = fetcher.try_get(1).unwrap().remove(0);
+    /// if !proxy.is_alive(Duration::from_secs(3)) {
+    ///     fetcher.report_failure(&proxy);
+    /// }
+    /// ```
+    pub fn is_alive(&self, timeout: Duration) -> bool {
+        TcpStream::connect_timeout(&self.socket, timeout).is_ok()
+    }
+
+    /// Wraps `connector` so that a `hyper` client built with it goes through this proxy.
+    ///
+    /// HTTP proxies are handled by `hyper-proxy` which tunnels requests with `CONNECT`, and SOCKS5
+    /// proxies are handled by `hyper-socks2`. Neither handles SOCKS4 proxies so those return an
+    /// [`UnsupportedProtocol`][UnsupportedProtocol] error. Note that TLS to the destination isn't
+    /// handled here, so wrap the returned connector with something like `hyper-tls` for HTTPS.
+    ///
+    /// ```no_run
+    /// use hyper::{client::HttpConnector, Body, Client, Uri};
+    /// use lead_oxide::{fetcher::Fetcher, proxy::HyperConnector};
+    ///
+    /// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// let proxy = Fetcher::default().try_get(1)?.remove(0);
+    /// let uri: Uri = "http://example.com".parse()?;
+    ///
+    /// // Each arm has a different connector type, so each one builds its own client
+    /// let resp = match proxy.hyper_connector(HttpConnector::new())? {
+    ///     HyperConnector::Http(connector) => {
+    ///         Client::builder().build::<_, Body>(connector).get(uri).await?
+    ///     }
+    ///     HyperConnector::Socks(connector) => {
+    ///         Client::builder().build::<_, Body>(connector).get(uri).await?
+    ///     }
+    /// };
+    /// println!("Got {}", resp.status());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "hyper")]
+    pub fn hyper_connector<C>(
+        &self,
+        connector: C,
+    ) -> Result<HyperConnector<C>, UnsupportedProtocol> {
+        let uri: hyper::Uri = self
+            .to_string()
+            .parse()
+            .expect("A socket address always forms a valid uri");
+
+        match self.protocol {
+            Protocol::Http => {
+                let proxy = hyper_proxy::Proxy::new(hyper_proxy::Intercept::All, uri);
+                Ok(HyperConnector::Http(
+                    hyper_proxy::ProxyConnector::from_proxy_unsecured(connector, proxy),
+                ))
+            }
+            Protocol::Socks5 => Ok(HyperConnector::Socks(hyper_socks2::SocksConnector {
+                proxy_addr: uri,
+                auth: None,
+                connector,
+            })),
+            Protocol::Socks4 => Err(UnsupportedProtocol(self.protocol)),
+        }
+    }
+}
+
+/// Orders proxies by their `time_to_connect` with the fastest first, and then by `last_checked`
+/// with the most recently checked first. Any remaining ties are broken by the other fields just so
+/// the ordering agrees with `==`. Since "less" means "better" here use
+/// [`Reverse`][std::cmp::Reverse] with a [`BinaryHeap`][std::collections::BinaryHeap] to pop the
+/// fastest proxies.
+///
+/// ```
+/// use lead_oxide::proxy::Proxy;
+/// use std::time::Duration;
+///
+/// let base = Proxy::builder().socket("1.2.3.4:1234".parse().unwrap()).build();
+/// let proxy = |secs| Proxy {
+///     time_to_connect: Duration::from_secs(secs),
+///     ..base.clone()
+/// };
+/// let mut proxies = vec![proxy(30), proxy(2), proxy(10)];
+/// proxies.sort();
+/// assert_eq!(proxies, [proxy(2), proxy(10), proxy(30)]);
+/// ```
+impl Ord for Proxy {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.time_to_connect
+            .cmp(&other.time_to_connect)
+            .then_with(|| other.last_checked.cmp(&self.last_checked))
+            .then_with(|| self.socket.cmp(&other.socket))
+            .then_with(|| self.country.cmp(&other.country))
+            .then_with(|| self.level.cmp(&other.level))
+            .then_with(|| self.protocol.cmp(&other.protocol))
+            .then_with(|| self.supports.cmp(&other.supports))
+    }
+}
+
+impl PartialOrd for Proxy {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Formats the proxy as a url like `http://1.2.3.4:8080` or `socks5://1.2.3.4:1080` depending on
+/// its `protocol`, which is the format most tools expect for a proxy.
+///
+/// ```
+/// use lead_oxide::{proxy::Proxy, types::Protocol};
+///
+/// let proxy = Proxy::builder()
+///     .socket("1.2.3.4:1080".parse().unwrap())
+///     .protocol(Protocol::Socks5)
+///     .build();
+/// assert_eq!(proxy.to_string(), "socks5://1.2.3.4:1080");
+/// ```
+impl fmt::Display for Proxy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}://{}", self.protocol.scheme(), self.socket)
+    }
+}
+
+/// Converts the proxy into a `reqwest::Proxy` that all of a client's requests go through. This
+/// uses the scheme from the proxy's `protocol`, and since `reqwest` doesn't handle SOCKS4 proxies
+/// those return an [`UnsupportedProtocol`][UnsupportedProtocol] error.
+///
+/// ```
+/// use lead_oxide::{proxy::Proxy, types::Protocol};
+/// use std::convert::TryFrom;
+///
+/// let proxy = Proxy::builder()
+///     .socket("1.2.3.4:1080".parse().unwrap())
+///     .protocol(Protocol::Socks5)
+///     .build();
+/// let client = reqwest::Client::builder()
+///     .proxy(reqwest::Proxy::try_from(&proxy).unwrap())
+///     .build()
+///     .unwrap();
+/// ```
+#[cfg(feature = "reqwest")]
+impl TryFrom<&Proxy> for reqwest::Proxy {
+    type Error = UnsupportedProtocol;
+
+    fn try_from(proxy: &Proxy) -> Result<Self, Self::Error> {
+        match proxy.protocol {
+            Protocol::Http | Protocol::Socks5 => Ok(reqwest::Proxy::all(proxy.to_string())
+                .expect("A socket address always forms a valid url")),
+            Protocol::Socks4 => Err(UnsupportedProtocol(proxy.protocol)),
+        }
+    }
+}
+
+/// A `hyper` connector that routes connections through a [`Proxy`][Proxy].
+///
+/// Constructed with [`Proxy::hyper_connector()`][Proxy::hyper_connector]. The variant depends on
+/// the protocol of the proxy since each is handled by a different crate.
+#[cfg(feature = "hyper")]
+#[derive(Clone, Debug)]
+pub enum HyperConnector<C> {
+    Http(hyper_proxy::ProxyConnector<C>),
+    Socks(hyper_socks2::SocksConnector<C>),
+}
+
+/// A builder for manually constructing a [`Proxy`][Proxy].
+///
+/// Constructed with `Proxy::builder()`. This is mostly useful for testing or seeding proxies from
+/// somewhere other than the API. Any field that isn't specified uses a default: an unspecified
+/// socket and country, a `last_checked` of now, an Anonymous HTTP proxy, no time to connect, and
+/// default [`Supports`][Supports].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProxyBuilder {
+    socket: Option<SocketAddr>,
+    country: Option<Country>,
+    last_checked: Option<NaiveDateTime>,
+    level: Option<Level>,
+    protocol: Option<Protocol>,
+    time_to_connect: Option<Duration>,
+    supports: Option<Supports>,
+}
+
+impl ProxyBuilder {
+    /// The socket address the proxy is listening on.
+    pub fn socket(mut self, socket: SocketAddr) -> Self {
+        self.socket = Some(socket);
+        self
+    }
+
+    /// The country the proxy is located in.
+    pub fn country(mut self, country: Country) -> Self {
+        self.country = Some(country);
+        self
+    }
+
+    /// The time when the proxy was last checked.
+    pub fn last_checked(mut self, last_checked: NaiveDateTime) -> Self {
+        self.last_checked = Some(last_checked);
+        self
+    }
+
+    /// The anonymity level of the proxy.
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// The protocol supported by the proxy.
+    pub fn protocol(mut self, protocol: Protocol) -> Self {
+        self.protocol = Some(protocol);
+        self
+    }
+
+    /// How long it took to connect to the proxy.
+    pub fn time_to_connect(mut self, time_to_connect: Duration) -> Self {
+        self.time_to_connect = Some(time_to_connect);
+        self
+    }
+
+    /// All the attributes that the proxy supports.
+    pub fn supports(mut self, supports: Supports) -> Self {
+        self.supports = Some(supports);
+        self
+    }
+
+    /// Constructs the `ProxyBuilder` into the corresponding [`Proxy`][Proxy] value.
+    pub fn build(self) -> Proxy {
+        Proxy {
+            socket: self
+                .socket
+                .unwrap_or_else(|| SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))),
+            country: self.country.unwrap_or(Country::Unspecified),
+            last_checked: self.last_checked.unwrap_or_else(|| Utc::now().naive_utc()),
+            level: self.level.unwrap_or(Level::Anonymous),
+            protocol: self.protocol.unwrap_or(Protocol::Http),
+            time_to_connect: self.time_to_connect.unwrap_or_default(),
+            supports: self.supports.unwrap_or_default(),
+        }
+    }
+}
+
+// A malformed row fails parsing the whole response instead of panicking
+impl TryFrom<RawProxy> for Proxy {
+    type Error = serde_json::Error;
+
+    fn try_from(raw: RawProxy) -> Result<Self, Self::Error> {
+        let last_checked =
+            NaiveDateTime::parse_from_str(&raw.last_checked, "%F %T").map_err(|_| {
+                de::Error::custom(format!(
+                    "The API returned an invalid time '{}'. Please raise an issue to address this \
+                     at {}",
+                    raw.last_checked, REPO_URI
+                ))
+            })?;
+
+        let secs_to_connect = raw.time_to_connect.parse().map_err(|_| {
+            de::Error::custom(format!(
+                "The API returned an invalid int '{}'. Please raise an issue to address this at {}",
+                raw.time_to_connect, REPO_URI
+            ))
+        })?;
         let time_to_connect = Duration::from_secs(secs_to_connect);
 
-        Self {
+        Ok(Self {
             socket: raw.socket,
             country: raw.country,
             last_checked,
@@ -100,26 +454,54 @@ impl From<RawProxy> for Proxy {
             protocol: raw.protocol,
             time_to_connect,
             supports: Supports::from(raw.supports),
-        }
+        })
     }
 }
 
 /// Internal
 pub(crate) fn proxies_from_json(json: &str) -> Result<Vec<Proxy>, serde_json::Error> {
     let resp: Response = serde_json::from_str(json)?;
-    Ok(resp
+    let proxies = resp
         .data
         .into_iter()
-        .map(Proxy::from)
-        // Just to play it safe we filter out any results with an incorrect country field. We could
-        // be smarter and only use this in the presence of a blocklist if this causes issues. Just
-        // to note this is typically less than 10% or responses.
+        .map(Proxy::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Just to play it safe we filter out any results with an incorrect country field. We could be
+    // smarter and only use this in the presence of a blocklist if this causes issues. Just to note
+    // this is typically less than 10% or responses.
+    Ok(proxies
+        .into_iter()
         .filter(|&Proxy { country, .. }| country != Country::Unspecified)
         .collect())
 }
 
+/// Returns the proxies in `current` that weren't already in `previous`.
+///
+/// Proxies are considered the same if they share a `socket` since the other information (like
+/// `last_checked`) changes each time the API checks them. This is handy when periodically
+/// refetching to find out which proxies are new since last time.
+///
+/// ```
+/// use lead_oxide::proxy::{self, Proxy};
+///
+/// let proxy = |socket: &str| Proxy::builder().socket(socket.parse().unwrap()).build();
+/// let previous = vec![proxy("1.2.3.4:80"), proxy("5.6.7.8:80")];
+/// let current = vec![proxy("5.6.7.8:80"), proxy("9.10.11.12:80")];
+///
+/// assert_eq!(proxy::new_proxies(&previous, &current), vec![current[1].clone()]);
+/// ```
+pub fn new_proxies(previous: &[Proxy], current: &[Proxy]) -> Vec<Proxy> {
+    let seen: HashSet<_> = previous.iter().map(|proxy| proxy.socket).collect();
+    current
+        .iter()
+        .filter(|proxy| !seen.contains(&proxy.socket))
+        .cloned()
+        .collect()
+}
+
 /// Represents all the attributes that the [`Proxy`][Proxy] supports.
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Supports {
     pub https: bool,
     pub get: bool,
@@ -130,6 +512,101 @@ pub struct Supports {
     pub connects_to_google: bool,
 }
 
+impl Supports {
+    /// Internal
+    fn flags(&self) -> [bool; 7] {
+        [
+            self.https,
+            self.get,
+            self.post,
+            self.cookies,
+            self.referer,
+            self.forwards_user_agent,
+            self.connects_to_google,
+        ]
+    }
+
+    /// Internal
+    pub(crate) fn contains(&self, other: &Supports) -> bool {
+        self.flags()
+            .iter()
+            .zip(other.flags().iter())
+            .all(|(&has, &wanted)| has || !wanted)
+    }
+
+    /// Internal
+    pub(crate) fn overlap(&self, other: &Supports) -> usize {
+        self.flags()
+            .iter()
+            .zip(other.flags().iter())
+            .filter(|(&has, &wanted)| has && wanted)
+            .count()
+    }
+}
+
+/// How much each attribute counts towards [`Proxy::quality_score_with`][Proxy::quality_score_with].
+///
+/// Weights are relative to each other, so only their proportions matter and they don't need to add
+/// up to anything in particular. They should all be non-negative. The default favors speed and
+/// freshness over anonymity and capabilities.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QualityWeights {
+    pub speed: f64,
+    pub freshness: f64,
+    pub level: f64,
+    pub supports: f64,
+}
+
+impl Default for QualityWeights {
+    fn default() -> Self {
+        Self {
+            speed: 0.4,
+            freshness: 0.3,
+            level: 0.2,
+            supports: 0.1,
+        }
+    }
+}
+
+/// Splits [`Supports`][Supports] into capabilities that are required and ones that are preferred.
+///
+/// Used with [`Fetcher::try_get_supporting`][crate::fetcher::Fetcher::try_get_supporting]. Proxies
+/// missing any of the `required` capabilities are excluded entirely, while the rest are ranked by
+/// how many of the `preferred` capabilities they have. Any field that is `false` is ignored.
+///
+/// ```
+/// use lead_oxide::proxy::{Supports, SupportsFilter};
+///
+/// // Cookies are a must, but referer support is nice to have
+/// let filter = SupportsFilter {
+///     required: Supports {
+///         cookies: true,
+///         ..Supports::default()
+///     },
+///     preferred: Supports {
+///         referer: true,
+///         ..Supports::default()
+///     },
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SupportsFilter {
+    pub required: Supports,
+    pub preferred: Supports,
+}
+
+impl SupportsFilter {
+    /// Internal
+    pub(crate) fn permits(&self, proxy: &Proxy) -> bool {
+        proxy.supports.contains(&self.required)
+    }
+
+    /// Internal
+    pub(crate) fn score(&self, proxy: &Proxy) -> usize {
+        proxy.supports.overlap(&self.preferred)
+    }
+}
+
 impl From<RawSupports> for Supports {
     fn from(raw: RawSupports) -> Self {
         let parse_field = |field| match field {
@@ -155,7 +632,7 @@ mod test {
     use super::*;
 
     use chrono::NaiveDate;
-    use std::{fs, path::Path};
+    use std::{cmp::Reverse, collections::BinaryHeap, fs, net::TcpListener, path::Path};
 
     #[test]
     fn deserialization() -> Result<(), serde_json::Error> {
@@ -166,12 +643,12 @@ mod test {
         // And now onto testing
         let proxies = proxies_from_json(&raw_response)?;
 
-        let date = NaiveDate::from_ymd(2020, 12, 13);
+        let date = NaiveDate::from_ymd_opt(2020, 12, 13).unwrap();
 
         let common = Proxy {
             socket: "1.2.3.4:1234".parse().unwrap(),
             country: Country::US,
-            last_checked: date.and_hms(0, 0, 0),
+            last_checked: date.and_hms_opt(0, 0, 0).unwrap(),
             level: Level::Elite,
             protocol: Protocol::Http,
             time_to_connect: Duration::from_secs(0),
@@ -189,13 +666,13 @@ mod test {
         let ideal = vec![
             Proxy {
                 socket: "67.225.164.154:80".parse().unwrap(),
-                last_checked: date.and_hms(20, 6, 41),
+                last_checked: date.and_hms_opt(20, 6, 41).unwrap(),
                 time_to_connect: Duration::from_secs(10),
                 ..common
             },
             Proxy {
                 socket: "35.181.4.4:80".parse().unwrap(),
-                last_checked: date.and_hms(20, 10, 11),
+                last_checked: date.and_hms_opt(20, 10, 11).unwrap(),
                 time_to_connect: Duration::from_secs(1),
                 supports: Supports {
                     forwards_user_agent: true,
@@ -206,7 +683,7 @@ mod test {
             Proxy {
                 socket: "89.24.76.185:32842".parse().unwrap(),
                 country: Country::CZ,
-                last_checked: date.and_hms(20, 1, 52),
+                last_checked: date.and_hms_opt(20, 1, 52).unwrap(),
                 protocol: Protocol::Socks5,
                 time_to_connect: Duration::from_secs(18),
                 ..common
@@ -214,7 +691,7 @@ mod test {
             Proxy {
                 socket: "125.99.120.166:40390".parse().unwrap(),
                 country: Country::IN,
-                last_checked: date.and_hms(20, 10, 11),
+                last_checked: date.and_hms_opt(20, 10, 11).unwrap(),
                 protocol: Protocol::Socks4,
                 time_to_connect: Duration::from_secs(14),
                 ..common
@@ -229,4 +706,394 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn unknown_country() {
+        let raw = |country: &str| {
+            serde_json::json!({
+                "ipPort": "1.2.3.4:80",
+                "country": country,
+                "last_checked": "2020-12-13 20:06:41",
+                "proxy_level": "elite",
+                "type": "http",
+                "speed": "10",
+                "support": {},
+            })
+        };
+
+        // Unknown codes fall back to unspecified instead of failing
+        for code in &["EU", "ZZ", "", "usa"] {
+            let raw_proxy: RawProxy = serde_json::from_value(raw(code)).unwrap();
+            assert_eq!(
+                Proxy::try_from(raw_proxy).unwrap().country,
+                Country::Unspecified
+            );
+        }
+        let raw_proxy: RawProxy = serde_json::from_value(raw("CA")).unwrap();
+        assert_eq!(Proxy::try_from(raw_proxy).unwrap().country, Country::CA);
+
+        // So the rest of the response is still usable
+        let response = serde_json::json!({ "data": [raw("XX"), raw("US")], "count": 2 });
+        let proxies = proxies_from_json(&response.to_string()).unwrap();
+        assert_eq!(proxies.len(), 1);
+        assert_eq!(proxies[0].country, Country::US);
+    }
+
+    #[test]
+    fn malformed() {
+        let raw = |last_checked: &str, speed: &str| {
+            serde_json::json!({
+                "ipPort": "1.2.3.4:80",
+                "country": "US",
+                "last_checked": last_checked,
+                "proxy_level": "elite",
+                "type": "http",
+                "speed": speed,
+                "support": {},
+            })
+        };
+        let parse = |raw_proxy| {
+            let response = serde_json::json!({ "data": [raw_proxy], "count": 1 });
+            proxies_from_json(&response.to_string())
+        };
+
+        // Bad values are a parse error instead of a panic
+        assert!(parse(raw("2020-12-13 20:06:41", "10")).is_ok());
+        let err = parse(raw("2020-12-13 20:06:41", "fast")).unwrap_err();
+        assert!(err.to_string().contains("invalid int 'fast'"), "{}", err);
+        let err = parse(raw("yesterday", "10")).unwrap_err();
+        assert!(
+            err.to_string().contains("invalid time 'yesterday'"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn serialization_round_trip() -> Result<(), serde_json::Error> {
+        let sample_file = Path::new("tests").join("samples").join("response.json");
+        let raw_response = fs::read_to_string(&sample_file).expect("Can't open the response file");
+        let proxy = proxies_from_json(&raw_response)?.remove(0);
+
+        let json = serde_json::to_value(&proxy)?;
+        assert_eq!(json["socket"], "67.225.164.154:80");
+        assert_eq!(json["country"], "US");
+        assert_eq!(json["level"], "elite");
+        assert_eq!(json["protocol"], "http");
+        assert_eq!(json["supports"]["cookies"], true);
+        assert_eq!(serde_json::from_value::<Proxy>(json)?, proxy);
+
+        Ok(())
+    }
+
+    #[test]
+    fn transparent() {
+        let raw = serde_json::json!({
+            "ipPort": "1.2.3.4:80",
+            "country": "US",
+            "last_checked": "2020-12-13 20:06:41",
+            "proxy_level": "transparent",
+            "type": "http",
+            "speed": "10",
+            "support": {},
+        });
+
+        // Transparent proxies don't break deserializing the rest of the response
+        let raw_proxy: RawProxy = serde_json::from_value(raw).unwrap();
+        assert_eq!(
+            Proxy::try_from(raw_proxy).unwrap().level,
+            Level::Transparent
+        );
+    }
+
+    #[test]
+    fn ipv6() {
+        let raw = serde_json::json!({
+            "ipPort": "[2001:db8::1]:8080",
+            "country": "US",
+            "last_checked": "2020-12-13 20:06:41",
+            "proxy_level": "elite",
+            "type": "http",
+            "speed": "10",
+            "support": {},
+        });
+
+        // Both address families deserialize and still form valid proxy urls
+        let raw_proxy: RawProxy = serde_json::from_value(raw).unwrap();
+        let proxy = Proxy::try_from(raw_proxy).unwrap();
+        assert_eq!(proxy.socket, "[2001:db8::1]:8080".parse().unwrap());
+        assert!(proxy.socket.is_ipv6());
+        assert_eq!(proxy.to_string(), "http://[2001:db8::1]:8080");
+    }
+
+    #[test]
+    fn is_alive() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy = Proxy::builder()
+            .socket(listener.local_addr().unwrap())
+            .build();
+        let timeout = Duration::from_secs(1);
+        assert!(proxy.is_alive(timeout));
+
+        // Nothing is listening anymore
+        drop(listener);
+        assert!(!proxy.is_alive(timeout));
+    }
+
+    #[test]
+    fn ordering() {
+        let date = NaiveDate::from_ymd_opt(2020, 12, 13).unwrap();
+        let proxy = |secs, hour| Proxy {
+            time_to_connect: Duration::from_secs(secs),
+            last_checked: date.and_hms_opt(hour, 0, 0).unwrap(),
+            ..Proxy::builder()
+                .socket("1.2.3.4:80".parse().unwrap())
+                .build()
+        };
+
+        // Fastest first, then the most recently checked
+        let mut proxies = vec![proxy(10, 3), proxy(1, 1), proxy(10, 5), proxy(5, 0)];
+        proxies.sort();
+        assert_eq!(
+            proxies,
+            [proxy(1, 1), proxy(5, 0), proxy(10, 5), proxy(10, 3)]
+        );
+
+        // Keeping the best few is just a heap away
+        let mut heap: BinaryHeap<_> = proxies.into_iter().map(Reverse).collect();
+        assert_eq!(heap.pop().map(|Reverse(proxy)| proxy), Some(proxy(1, 1)));
+
+        // Ties fall back to the other fields so the ordering matches equality
+        let other = Proxy {
+            socket: "1.2.3.4:81".parse().unwrap(),
+            ..proxy(1, 1)
+        };
+        assert_ne!(proxy(1, 1).cmp(&other), Ordering::Equal);
+        assert_eq!(proxy(1, 1).cmp(&proxy(1, 1)), Ordering::Equal);
+    }
+
+    #[test]
+    fn display() {
+        let proxy = |protocol| {
+            Proxy::builder()
+                .socket("1.2.3.4:4321".parse().unwrap())
+                .protocol(protocol)
+                .build()
+        };
+
+        assert_eq!(proxy(Protocol::Http).to_string(), "http://1.2.3.4:4321");
+        assert_eq!(proxy(Protocol::Socks4).to_string(), "socks4://1.2.3.4:4321");
+        assert_eq!(proxy(Protocol::Socks5).to_string(), "socks5://1.2.3.4:4321");
+    }
+
+    #[test]
+    fn builder() {
+        let socket = "1.2.3.4:1234".parse().unwrap();
+
+        // Unspecified fields get filled in with defaults
+        let before = Utc::now().naive_utc();
+        let basic = Proxy::builder().socket(socket).build();
+        let after = Utc::now().naive_utc();
+
+        assert_eq!(basic.socket, socket);
+        assert_eq!(basic.country, Country::Unspecified);
+        assert!(basic.last_checked >= before && basic.last_checked <= after);
+        assert_eq!(basic.level, Level::Anonymous);
+        assert_eq!(basic.protocol, Protocol::Http);
+        assert_eq!(basic.time_to_connect, Duration::from_secs(0));
+        assert_eq!(basic.supports, Supports::default());
+
+        // And any field can be overridden
+        let last_checked = NaiveDate::from_ymd_opt(2020, 12, 13)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let supports = Supports {
+            https: true,
+            ..Supports::default()
+        };
+        let custom = Proxy::builder()
+            .socket(socket)
+            .country(Country::CA)
+            .last_checked(last_checked)
+            .level(Level::Elite)
+            .protocol(Protocol::Socks5)
+            .time_to_connect(Duration::from_secs(5))
+            .supports(supports)
+            .build();
+
+        assert_eq!(
+            custom,
+            Proxy {
+                socket,
+                country: Country::CA,
+                last_checked,
+                level: Level::Elite,
+                protocol: Protocol::Socks5,
+                time_to_connect: Duration::from_secs(5),
+                supports,
+            }
+        );
+    }
+
+    #[test]
+    fn new_since_previous() {
+        let proxy = |port, country| Proxy {
+            socket: SocketAddr::from(([1, 2, 3, 4], port)),
+            country,
+            last_checked: NaiveDate::from_ymd_opt(2020, 12, 13)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+            level: Level::Elite,
+            protocol: Protocol::Http,
+            time_to_connect: Duration::from_secs(1),
+            supports: Supports::default(),
+        };
+
+        let previous = vec![proxy(1, Country::US), proxy(2, Country::US)];
+        // Proxy 2 was rechecked so its other information differs but it's still the same proxy
+        let current = vec![
+            proxy(2, Country::CA),
+            proxy(3, Country::US),
+            proxy(1, Country::US),
+            proxy(4, Country::US),
+        ];
+
+        assert_eq!(
+            new_proxies(&previous, &current),
+            vec![proxy(3, Country::US), proxy(4, Country::US)]
+        );
+        assert!(new_proxies(&current, &previous).is_empty());
+        assert_eq!(new_proxies(&[], &previous), previous);
+    }
+
+    #[test]
+    fn quality_score() {
+        let now = NaiveDate::from_ymd_opt(2020, 12, 13)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+        let minutes_ago = |minutes| now - chrono::Duration::minutes(minutes);
+        let proxy = |secs, minutes, level, supports| Proxy {
+            last_checked: minutes_ago(minutes),
+            level,
+            time_to_connect: Duration::from_secs(secs),
+            supports,
+            ..Proxy::builder()
+                .socket("1.2.3.4:1234".parse().unwrap())
+                .build()
+        };
+        let everything = Supports {
+            https: true,
+            get: true,
+            post: true,
+            cookies: true,
+            referer: true,
+            forwards_user_agent: true,
+            connects_to_google: true,
+        };
+        let score = |proxy: &Proxy| proxy.quality_score_at(&QualityWeights::default(), now);
+
+        let best = proxy(0, 0, Level::Elite, everything);
+        let worst = proxy(60, 60, Level::Anonymous, Supports::default());
+        assert_eq!(score(&best), 1.0);
+        // Anonymous still counts for a little
+        assert!((score(&worst) - 0.1).abs() < 1e-9, "{}", score(&worst));
+        // Anything beyond the API's bounds doesn't get any worse
+        assert_eq!(
+            score(&proxy(120, 600, Level::Anonymous, Supports::default())),
+            score(&worst)
+        );
+
+        // Faster, fresher, more anonymous, and more capable are all better
+        let middle = proxy(30, 30, Level::Anonymous, Supports::default());
+        for better in &[
+            proxy(10, 30, Level::Anonymous, Supports::default()),
+            proxy(30, 5, Level::Anonymous, Supports::default()),
+            proxy(30, 30, Level::Elite, Supports::default()),
+            proxy(30, 30, Level::Anonymous, everything),
+        ] {
+            assert!(score(better) > score(&middle), "{:#?}", better);
+        }
+
+        // Speed matters most by default, but the weights can change that
+        let fast = proxy(1, 50, Level::Anonymous, Supports::default());
+        let fresh = proxy(50, 1, Level::Anonymous, Supports::default());
+        assert!(score(&fast) > score(&fresh));
+        let freshness_first = QualityWeights {
+            freshness: 1.0,
+            ..QualityWeights::default()
+        };
+        assert!(
+            fresh.quality_score_at(&freshness_first, now)
+                > fast.quality_score_at(&freshness_first, now)
+        );
+    }
+
+    #[cfg(feature = "hyper")]
+    #[test]
+    fn hyper_connector() {
+        let proxy = |protocol| {
+            Proxy::builder()
+                .socket("1.2.3.4:1234".parse().unwrap())
+                .protocol(protocol)
+                .build()
+        };
+
+        // HTTP proxies intercept everything through the proxy
+        match proxy(Protocol::Http).hyper_connector(()) {
+            Ok(HyperConnector::Http(connector)) => {
+                let proxies = connector.proxies();
+                assert_eq!(proxies.len(), 1);
+                assert_eq!(proxies[0].uri(), "http://1.2.3.4:1234/");
+                assert!(matches!(
+                    proxies[0].intercept(),
+                    hyper_proxy::Intercept::All
+                ));
+            }
+            other => panic!("Expected an HTTP connector, got {:?}", other),
+        }
+
+        // SOCKS5 proxies go through a SOCKS connector
+        match proxy(Protocol::Socks5).hyper_connector(()) {
+            Ok(HyperConnector::Socks(connector)) => {
+                assert_eq!(connector.proxy_addr, "socks5://1.2.3.4:1234");
+                assert_eq!(connector.auth, None);
+            }
+            other => panic!("Expected a SOCKS connector, got {:?}", other),
+        }
+
+        // And SOCKS4 isn't supported at all
+        assert_eq!(
+            proxy(Protocol::Socks4).hyper_connector(()).unwrap_err(),
+            UnsupportedProtocol(Protocol::Socks4)
+        );
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn reqwest_proxy() {
+        let proxy = |protocol| {
+            Proxy::builder()
+                .socket("1.2.3.4:1234".parse().unwrap())
+                .protocol(protocol)
+                .build()
+        };
+
+        // The scheme comes from the protocol
+        for (protocol, url) in [
+            (Protocol::Http, "http://1.2.3.4:1234"),
+            (Protocol::Socks5, "socks5://1.2.3.4:1234"),
+        ] {
+            let converted = reqwest::Proxy::try_from(&proxy(protocol)).unwrap();
+            let debug = format!("{:?}", converted);
+            assert!(debug.contains(url), "{}", debug);
+        }
+
+        assert_eq!(
+            reqwest::Proxy::try_from(&proxy(Protocol::Socks4)).unwrap_err(),
+            UnsupportedProtocol(Protocol::Socks4)
+        );
+    }
 }
@@ -0,0 +1,678 @@
+//! [`Session`][Session]s hold the state that is shared between [`Fetcher`][crate::fetcher::Fetcher]s.
+//!
+//! Most of this state is used to coordinate the rate-limiting between keyless fetchers, but it also
+//! keeps track of how many requests have been made within the last day.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    constants, errors::ApiError, fetcher::Fetcher, opts::Opts, policy::FetchPolicy,
+    tiered::TieredFetcher,
+};
+
+use iso_country::Country;
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+lazy_static! {
+    pub(crate) static ref GLOBAL: Session = Session::new();
+}
+
+/// Handles all the waiting done by [`Fetcher`][crate::fetcher::Fetcher]s from a
+/// [`Session`][Session].
+///
+/// This covers the delay between keyless requests, waiting out a `Retry-After` from the API, and
+/// the output rate from [`Fetcher::with_output_rate`][crate::fetcher::Fetcher::with_output_rate].
+/// The default [`ThreadSleeper`][ThreadSleeper] just blocks the current thread, but a custom
+/// `Sleeper` can be set with [`Session::with_sleeper`][Session::with_sleeper] to do things like
+/// record the requested durations instead of actually waiting.
+pub trait Sleeper: fmt::Debug + Send + Sync {
+    /// Waits for `duration`.
+    fn sleep(&self, duration: Duration);
+}
+
+/// A [`Sleeper`][Sleeper] that blocks the current thread with `std::thread::sleep`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThreadSleeper;
+
+impl Sleeper for ThreadSleeper {
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// State shared between [`Fetcher`][crate::fetcher::Fetcher]s.
+///
+/// All fetchers created from the same `Session` (including across threads) will coordinate their
+/// delays with each other while fetchers from different `Session`s will not, so it's best to stick
+/// to a single `Session` per program. `Fetcher::new` uses a global `Session` so this is only needed
+/// when you want to configure the shared state.
+///
+/// ```
+/// use lead_oxide::{opts::Opts, session::Session};
+///
+/// let session = Session::new().with_daily_limit(50);
+/// let mut fetcher = session.fetcher(Opts::default());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Session {
+    pub(crate) last_fetched: Arc<Mutex<Option<Instant>>>,
+    requests: Arc<Mutex<VecDeque<Instant>>>,
+    latencies: Arc<Mutex<HashMap<Country, Latency>>>,
+    daily_limit: Option<usize>,
+    // Shared by every fetcher from the session so connections get reused between requests
+    agent: ureq::Agent,
+    uri: String,
+    contention: Contention,
+    sleeper: Arc<dyn Sleeper>,
+    policy: FetchPolicy,
+}
+
+/// Internal
+#[derive(Clone, Debug, Default)]
+struct Contention {
+    threshold: Option<usize>,
+    waiting: Arc<AtomicUsize>,
+    warned: Arc<AtomicBool>,
+}
+
+/// Internal
+pub(crate) struct Contender<'a> {
+    waiting: &'a AtomicUsize,
+}
+
+impl Drop for Contender<'_> {
+    fn drop(&mut self) {
+        self.waiting.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Session {
+    /// Creates a new `Session` with no daily limit. The first fetch from a new `Session` won't be
+    /// delayed.
+    pub fn new() -> Self {
+        Self {
+            last_fetched: Arc::new(Mutex::new(None)),
+            requests: Arc::new(Mutex::new(VecDeque::new())),
+            latencies: Arc::new(Mutex::new(HashMap::new())),
+            daily_limit: None,
+            agent: ureq::agent(),
+            uri: constants::API_URI.to_string(),
+            contention: Contention::default(),
+            sleeper: Arc::new(ThreadSleeper),
+            policy: FetchPolicy::default(),
+        }
+    }
+
+    /// Sets the maximum number of requests that can be made within a rolling 24 hour window.
+    /// Fetching beyond this limit will return an
+    /// [`ApiError::DailyLimitReached`][crate::errors::ApiError::DailyLimitReached] without making
+    /// a request. This is useful for keeping within the keyless daily limit instead of getting
+    /// errors from the API.
+    pub fn with_daily_limit(mut self, daily_limit: usize) -> Self {
+        self.daily_limit = Some(daily_limit);
+        self
+    }
+
+    /// Treats `last_fetched` as the time of the most recent request, so the first keyless fetch from
+    /// this `Session` is delayed until the usual delay after it has passed. By default a new
+    /// `Session` can fetch right away, but this is useful when something else (like another
+    /// process) just made a request.
+    ///
+    /// ```
+    /// use lead_oxide::session::Session;
+    /// use std::time::Instant;
+    ///
+    /// // Wait out the delay even on the first fetch
+    /// let session = Session::new().fresh_after(Instant::now());
+    /// ```
+    pub fn fresh_after(self, last_fetched: Instant) -> Self {
+        *self
+            .last_fetched
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(last_fetched);
+        self
+    }
+
+    /// Sets the [`FetchPolicy`][crate::policy::FetchPolicy] used by every
+    /// [`Fetcher`][crate::fetcher::Fetcher] created from this `Session`. Individual fetchers can
+    /// still override it with [`Fetcher::with_policy`][crate::fetcher::Fetcher::with_policy].
+    pub fn with_policy(mut self, policy: FetchPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets how long keyless fetchers from this `Session` wait between requests to avoid rate
+    /// limiting. This is the same as setting [`delay`][crate::policy::FetchPolicyBuilder::delay] on
+    /// the `Session`'s policy, so it defaults to 1.1 seconds and it needs to be set before any
+    /// fetchers are created. Be careful going lower since the rate limiting is quite strict.
+    ///
+    /// ```
+    /// use lead_oxide::session::Session;
+    /// use std::time::Duration;
+    ///
+    /// let session = Session::new().with_delay(Duration::from_secs(2));
+    /// ```
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.policy.delay = delay;
+        self
+    }
+
+    /// Uses `agent` for all requests to the API instead of building one internally. This allows
+    /// for using an agent that was already configured with things like custom headers or a proxy.
+    /// Either way a single agent is shared by every fetcher from the `Session` so that connections
+    /// to the API are kept alive between requests.
+    pub fn with_agent(mut self, agent: ureq::Agent) -> Self {
+        self.agent = agent;
+        self
+    }
+
+    /// Sends requests to `uri` instead of pubproxy.com. This allows for pointing fetchers at a local
+    /// mock server or a mirror of the API. Each [`Fetcher`][crate::fetcher::Fetcher] keeps its own
+    /// copy when it's created, and it can be changed per-fetcher with
+    /// [`Fetcher::with_uri`][crate::fetcher::Fetcher::with_uri].
+    ///
+    /// ```
+    /// use lead_oxide::session::Session;
+    ///
+    /// let session = Session::new().with_uri("http://localhost:8080/api/proxy");
+    /// ```
+    pub fn with_uri(mut self, uri: impl Into<String>) -> Self {
+        self.uri = uri.into();
+        self
+    }
+
+    /// Uses `sleeper` for all of the waiting done by fetchers from this `Session` instead of
+    /// blocking the current thread.
+    ///
+    /// ```
+    /// use lead_oxide::session::{Session, Sleeper};
+    /// use std::time::Duration;
+    ///
+    /// #[derive(Debug)]
+    /// struct Impatient;
+    ///
+    /// impl Sleeper for Impatient {
+    ///     fn sleep(&self, duration: Duration) {
+    ///         println!("Not waiting for {:?}", duration);
+    ///     }
+    /// }
+    ///
+    /// let session = Session::new().with_sleeper(Impatient);
+    /// ```
+    pub fn with_sleeper(mut self, sleeper: impl Sleeper + 'static) -> Self {
+        self.sleeper = Arc::new(sleeper);
+        self
+    }
+
+    /// Logs a one-time warning when more than `threshold` threads are waiting on this `Session`'s
+    /// delay at the same time. Keyless fetches are always done one at a time per `Session`, so
+    /// sharing a `Session` between many threads only queues them up instead of speeding anything
+    /// up. The warning is emitted through the `log` crate when the `log` feature is enabled.
+    pub fn with_contention_warning(mut self, threshold: usize) -> Self {
+        self.contention.threshold = Some(threshold);
+        self
+    }
+
+    /// Creates a [`Fetcher`][crate::fetcher::Fetcher] that uses this `Session`.
+    pub fn fetcher(&self, opts: Opts) -> Fetcher {
+        Fetcher::with_session(opts, self.clone())
+    }
+
+    /// Creates a [`TieredFetcher`][crate::tiered::TieredFetcher] that uses this `Session`.
+    pub fn tiered_fetcher(&self, tiers: Vec<Opts>) -> TieredFetcher {
+        TieredFetcher::with_session(tiers, self.clone())
+    }
+
+    /// Checks if the API is actually honoring `api_key` by making a single request with it. The key
+    /// is considered honored if the response has more proxies than a keyless request could return,
+    /// counting any that the `Fetcher` would drop as repeats.
+    /// An invalid key or getting rate-limited (which shouldn't happen with a key) means the key
+    /// isn't being honored, while any other error is returned. This is worth calling at startup
+    /// since a `Fetcher` with a key skips the keyless delays.
+    ///
+    /// ```no_run
+    /// use lead_oxide::session::Session;
+    ///
+    /// let session = Session::new();
+    /// if !session.verify_key("<key>".to_string()).unwrap() {
+    ///     eprintln!("The API key isn't being honored");
+    /// }
+    /// ```
+    pub fn verify_key(&self, api_key: String) -> Result<bool, ApiError> {
+        let opts = Opts::builder().api_key(api_key).build();
+        let free_limit = Opts::default().limit() as usize;
+
+        // Go off of the raw response since repeats still show that the key raised the limit
+        match self.fetcher(opts.clone()).request(&opts) {
+            Ok(proxies) => Ok(proxies.len() > free_limit),
+            Err(ApiError::NoProxy) | Err(ApiError::ApiKey) | Err(ApiError::RateLimit) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Lets the `Session` know that a request was just made to the API outside of this library, so
+    /// the next keyless fetch still waits out the usual delay after it. The request also counts
+    /// towards [`requests_today`][Session::requests_today] and any daily limit since the API
+    /// counts it all the same.
+    ///
+    /// ```
+    /// use lead_oxide::session::Session;
+    ///
+    /// let session = Session::new();
+    /// // ... a custom request to pubproxy
+    /// session.record_external_fetch();
+    /// assert_eq!(session.requests_today(), 1);
+    /// ```
+    pub fn record_external_fetch(&self) {
+        *self
+            .last_fetched
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(Instant::now());
+        self.record_request();
+    }
+
+    /// The number of requests made within the last 24 hours.
+    pub fn requests_today(&self) -> usize {
+        self.requests_at(Instant::now())
+    }
+
+    /// The average time the API took to respond to requests for proxies in `country`, or `None`
+    /// if there haven't been any. Only requests that use an allowlist of countries are timed, and
+    /// each request is attributed to every country in its allowlist.
+    ///
+    /// ```
+    /// use iso_country::Country;
+    /// use lead_oxide::session::Session;
+    ///
+    /// let session = Session::new();
+    /// // Nothing has been fetched yet
+    /// assert_eq!(session.country_latency(Country::JP), None);
+    /// ```
+    pub fn country_latency(&self, country: Country) -> Option<Duration> {
+        self.lock_latencies()
+            .get(&country)
+            .map(|latency| latency.total / latency.count)
+    }
+
+    /// Internal
+    pub(crate) fn daily_limit(&self) -> Option<usize> {
+        self.daily_limit
+    }
+
+    /// Internal
+    pub(crate) fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Internal
+    pub(crate) fn policy(&self) -> &FetchPolicy {
+        &self.policy
+    }
+
+    /// Internal
+    pub(crate) fn time_until_ready(&self, delay: Duration) -> Duration {
+        let last_fetched = self
+            .last_fetched
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match *last_fetched {
+            Some(last_fetched) => delay
+                .checked_sub(last_fetched.elapsed())
+                .unwrap_or_else(|| Duration::from_secs(0)),
+            None => Duration::from_secs(0),
+        }
+    }
+
+    /// Internal
+    pub(crate) fn get(&self, uri: &str) -> ureq::Request {
+        self.agent.get(uri)
+    }
+
+    /// Internal
+    pub(crate) fn sleep(&self, duration: Duration) {
+        self.sleeper.sleep(duration);
+    }
+
+    /// Internal
+    pub(crate) fn contend(&self) -> Contender<'_> {
+        let Contention {
+            threshold,
+            waiting,
+            warned,
+        } = &self.contention;
+        let contending = waiting.fetch_add(1, Ordering::SeqCst) + 1;
+
+        if let Some(threshold) = *threshold {
+            if contending > threshold && !warned.swap(true, Ordering::SeqCst) {
+                warn!(
+                    "{} threads are waiting on the same session's delay. Keyless fetches from a \
+                     session are done one at a time, so consider spreading work across sessions or \
+                     using an API key",
+                    contending
+                );
+            }
+        }
+
+        Contender { waiting }
+    }
+
+    /// Internal
+    pub(crate) fn record_request(&self) {
+        self.lock_requests().push_back(Instant::now());
+    }
+
+    /// Internal
+    pub(crate) fn record_latency(&self, countries: &[Country], elapsed: Duration) {
+        let mut latencies = self.lock_latencies();
+        for &country in countries {
+            let latency = latencies.entry(country).or_default();
+            latency.total += elapsed;
+            latency.count += 1;
+        }
+    }
+
+    // The window is relative to `now` to allow for testing the window moving forward
+    fn requests_at(&self, now: Instant) -> usize {
+        let mut requests = self.lock_requests();
+        while let Some(&oldest) = requests.front() {
+            if now.duration_since(oldest) >= DAY {
+                requests.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        requests.len()
+    }
+
+    fn lock_requests(&self) -> MutexGuard<'_, VecDeque<Instant>> {
+        // The list is always left in a valid state so a poisoned lock is fine to use
+        self.requests
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock_latencies(&self) -> MutexGuard<'_, HashMap<Country, Latency>> {
+        // Same as with the request list
+        self.latencies
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Internal
+#[derive(Clone, Copy, Debug, Default)]
+struct Latency {
+    total: Duration,
+    count: u32,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{sync::Barrier, thread};
+
+    use crate::{errors::ApiError, mock, types::Countries};
+
+    #[test]
+    fn daily_window() {
+        let session = Session::new();
+        let start = Instant::now();
+
+        session.record_request();
+        session.record_request();
+        assert_eq!(session.requests_at(start), 2);
+
+        // Requests are still counted up until they are a day old
+        assert_eq!(session.requests_at(start + DAY - Duration::from_secs(1)), 2);
+        // and then they drop out of the window
+        assert_eq!(session.requests_at(start + DAY + Duration::from_secs(1)), 0);
+        assert_eq!(session.requests_today(), 0);
+    }
+
+    #[test]
+    fn daily_limit() {
+        let session = Session::new().with_daily_limit(2);
+        let mut fetcher = session.fetcher(Opts::default());
+
+        // Each of these needs a fresh request
+        fetcher.try_get(1).unwrap();
+        fetcher.try_get(5).unwrap();
+        assert_eq!(session.requests_today(), 2);
+
+        // Proxies still in the internal list can be used
+        assert_eq!(fetcher.try_get(4).unwrap().len(), 4);
+
+        // But no more requests can be made
+        match fetcher.try_get(1) {
+            Err(ApiError::DailyLimitReached { limit }) => assert_eq!(limit, 2),
+            other => panic!("Expected `DailyLimitReached`, got {:?}", other),
+        }
+        assert_eq!(session.requests_today(), 2);
+    }
+
+    #[test]
+    fn custom_delay() {
+        let delay = constants::DELAY * 3;
+        let session = Session::new().with_delay(delay);
+        assert_eq!(session.policy().delay, delay);
+        let mut fetcher = session.fetcher(Opts::default());
+
+        // Keyless requests wait out the custom delay
+        let start = Instant::now();
+        fetcher.warm().unwrap();
+        fetcher.warm().unwrap();
+        assert!(start.elapsed() >= delay, "{:?}", start.elapsed());
+
+        // The rest of the policy is left alone
+        assert_eq!(
+            session.policy().max_attempts,
+            FetchPolicy::default().max_attempts
+        );
+    }
+
+    #[test]
+    fn custom_agent() {
+        let agent = ureq::agent().set("X-Custom", "value").build();
+        let session = Session::new().with_agent(agent);
+
+        let request = session.get(constants::API_URI);
+        assert_eq!(request.header("X-Custom"), Some("value"));
+
+        // The default agent doesn't have it
+        let request = Session::new().get(constants::API_URI);
+        assert_eq!(request.header("X-Custom"), None);
+    }
+
+    #[test]
+    fn shared_between_fetchers() {
+        let session = Session::new();
+        let mut fetcher1 = session.fetcher(Opts::default());
+        let mut fetcher2 = session.fetcher(Opts::default());
+
+        fetcher1.try_get(1).unwrap();
+        fetcher2.try_get(1).unwrap();
+        assert_eq!(session.requests_today(), 2);
+
+        // Separate sessions track separately
+        assert_eq!(Session::new().requests_today(), 0);
+    }
+
+    #[test]
+    fn country_latency() {
+        let session = Session::new();
+        let allow = |countries: &[Country]| {
+            Opts::builder()
+                .countries(Countries::allow().countries(countries))
+                .build()
+        };
+
+        // Each request is attributed to every country in the allowlist
+        mock::delay_next(Duration::from_millis(50));
+        session.fetcher(allow(&[Country::JP])).try_get(1).unwrap();
+        mock::delay_next(Duration::from_millis(10));
+        session
+            .fetcher(allow(&[Country::JP, Country::US]))
+            .try_get(1)
+            .unwrap();
+
+        let jp = session.country_latency(Country::JP).unwrap();
+        let us = session.country_latency(Country::US).unwrap();
+        assert!(jp >= Duration::from_millis(30), "JP latency: {:?}", jp);
+        assert!(us >= Duration::from_millis(10), "US latency: {:?}", us);
+        assert!(jp > us);
+
+        // Blocklists don't say where the proxies are from so they aren't tracked
+        session.fetcher(Opts::default()).try_get(1).unwrap();
+        assert_eq!(session.country_latency(Country::CA), None);
+    }
+
+    #[test]
+    fn fresh_after() {
+        let time_first_fetch = |session: Session| {
+            let start = Instant::now();
+            session.fetcher(Opts::default()).try_get(1).unwrap();
+            start.elapsed()
+        };
+
+        // New sessions can fetch right away
+        assert!(time_first_fetch(Session::new()) < constants::DELAY);
+        // but can be told that a request was just made
+        let elapsed = time_first_fetch(Session::new().fresh_after(Instant::now()));
+        assert!(elapsed >= constants::DELAY, "{:?}", elapsed);
+    }
+
+    #[test]
+    fn external_fetch() {
+        let session = Session::new();
+        let mut fetcher = session.fetcher(Opts::default());
+        fetcher.fetch_page().unwrap();
+
+        // Waiting out the delay means the next fetch is immediate
+        thread::sleep(constants::DELAY);
+        let start = Instant::now();
+        fetcher.fetch_page().unwrap();
+        assert!(start.elapsed() < constants::DELAY);
+
+        // Unless something else just made a request
+        thread::sleep(constants::DELAY);
+        session.record_external_fetch();
+        let start = Instant::now();
+        fetcher.fetch_page().unwrap();
+        assert!(start.elapsed() >= constants::DELAY);
+        assert_eq!(session.requests_today(), 4);
+    }
+
+    #[test]
+    fn sleeper() {
+        #[derive(Debug, Default)]
+        struct Recording(Arc<Mutex<Vec<Duration>>>);
+
+        impl Sleeper for Recording {
+            fn sleep(&self, duration: Duration) {
+                self.0.lock().unwrap().push(duration);
+            }
+        }
+
+        let recording = Recording::default();
+        let slept = Arc::clone(&recording.0);
+        let session = Session::new().with_sleeper(recording);
+        let mut fetcher = session.fetcher(Opts::default());
+
+        // The first request isn't delayed, but each following one is
+        let start = Instant::now();
+        for _ in 0..3 {
+            fetcher.fetch_page().unwrap();
+        }
+        // and nothing actually waited
+        assert!(start.elapsed() < constants::DELAY);
+
+        let slept = slept.lock().unwrap();
+        assert_eq!(slept.len(), 2);
+        for &duration in slept.iter() {
+            assert!(duration <= constants::DELAY);
+            assert!(duration > constants::DELAY / 2, "{:?}", duration);
+        }
+    }
+
+    #[test]
+    fn verify_key() -> Result<(), ApiError> {
+        let session = Session::new();
+
+        // A full premium page means the key works
+        assert!(session.verify_key("<key>".to_string())?);
+
+        // Even when the page is full of repeats
+        mock::push_proxies(&vec![mock::default_proxy(); 20]);
+        assert!(session.verify_key("<key>".to_string())?);
+
+        // Only a keyless page worth of proxies means it's being ignored
+        mock::push_proxies(&mock::default_page(5));
+        assert!(!session.verify_key("<key>".to_string())?);
+
+        // and an invalid key obviously isn't honored
+        mock::push_response(
+            200,
+            "Invalid API. Get your API to make unlimited requests at http://pubproxy.com/#premium",
+        );
+        assert!(!session.verify_key("<key>".to_string())?);
+
+        // Other errors are still returned
+        mock::push_response(500, "Internal Server Error");
+        assert!(session.verify_key("<key>".to_string()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn contention_warning() {
+        #[cfg(feature = "log")]
+        mock::init_logger();
+        let session = Session::new().with_contention_warning(3);
+        let barrier = Arc::new(Barrier::new(5));
+
+        let handles: Vec<_> = (0..5)
+            .map(|_| {
+                let session = session.clone();
+                let barrier = Arc::clone(&barrier);
+                thread::spawn(move || {
+                    barrier.wait();
+                    session.fetcher(Opts::default()).try_get(1).unwrap();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // Only warned the one time
+        #[cfg(feature = "log")]
+        {
+            let warnings: Vec<_> = mock::logs()
+                .into_iter()
+                .filter(|log| log.contains("waiting on the same session"))
+                .collect();
+            assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        }
+
+        // and nothing is left waiting afterwards
+        assert_eq!(session.contention.waiting.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,113 @@
+//! Internal limiting of how quickly proxies are handed out by a [`Fetcher`][crate::fetcher::Fetcher].
+
+use std::{
+    collections::VecDeque,
+    num::NonZeroU32,
+    time::{Duration, Instant},
+};
+
+/// Internal
+///
+/// A sliding window over how many proxies have been returned recently
+#[derive(Clone, Debug)]
+pub(crate) struct OutputRate {
+    count: usize,
+    per: Duration,
+    served: VecDeque<(Instant, usize)>,
+}
+
+impl OutputRate {
+    pub fn new(count: NonZeroU32, per: Duration) -> Self {
+        Self {
+            count: count.get() as usize,
+            per,
+            served: VecDeque::new(),
+        }
+    }
+
+    /// Reserves `amount` proxies to be served returning how long to wait from `now` before they can
+    /// be served. Requests larger than the rate wait for the window to be fully empty instead.
+    pub fn reserve(&mut self, amount: usize, now: Instant) -> Duration {
+        if amount == 0 {
+            return Duration::from_secs(0);
+        }
+
+        let allowed = self.count.saturating_sub(amount);
+        let mut at = now;
+
+        loop {
+            // Forget about anything that already left the window
+            while let Some(&(served_at, _)) = self.served.front() {
+                if at.duration_since(served_at) >= self.per {
+                    self.served.pop_front();
+                } else {
+                    break;
+                }
+            }
+
+            let in_window: usize = self.served.iter().map(|&(_, amount)| amount).sum();
+            match self.served.front() {
+                // Wait for the oldest entry to leave the window
+                Some(&(served_at, _)) if in_window > allowed => at = served_at + self.per,
+                _ => break,
+            }
+        }
+
+        self.served.push_back((at, amount));
+        at.duration_since(now)
+    }
+
+    /// Records that `amount` proxies were served at `now` regardless of the rate
+    pub fn record(&mut self, amount: usize, now: Instant) {
+        if amount > 0 {
+            // Reservations can be queued past `now`, so keep the window sorted
+            let index = self
+                .served
+                .partition_point(|&(served_at, _)| served_at <= now);
+            self.served.insert(index, (now, amount));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEC: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn sliding_window() {
+        let mut rate = OutputRate::new(NonZeroU32::new(2).unwrap(), SEC);
+        let start = Instant::now();
+
+        // Can burst up to the rate
+        assert_eq!(rate.reserve(2, start), Duration::from_secs(0));
+        // But then has to wait for the window to move
+        assert_eq!(rate.reserve(1, start), SEC);
+        assert_eq!(rate.reserve(1, start + SEC), Duration::from_secs(0));
+        assert_eq!(rate.reserve(1, start + SEC), SEC);
+        // Nothing is served so nothing is reserved
+        assert_eq!(rate.reserve(0, start + SEC), Duration::from_secs(0));
+        // Recording ignores the rate, but still counts towards it
+        rate.record(2, start + SEC * 3);
+        assert_eq!(rate.reserve(1, start + SEC * 3), SEC);
+
+        // Large requests only go through when the window is empty
+        let later = start + 10 * SEC;
+        assert_eq!(rate.reserve(5, later), Duration::from_secs(0));
+        assert_eq!(rate.reserve(1, later), SEC);
+    }
+
+    #[test]
+    fn record_behind_reservation() {
+        let mut rate = OutputRate::new(NonZeroU32::new(3).unwrap(), SEC);
+        let start = Instant::now();
+
+        assert_eq!(rate.reserve(3, start), Duration::from_secs(0));
+        // Queued up to be served a second from now
+        assert_eq!(rate.reserve(2, start), SEC);
+        // Recorded before the queued reservation, so it leaves the window first
+        rate.record(1, start + SEC / 2);
+        assert_eq!(rate.reserve(1, start + SEC * 8 / 5), Duration::from_secs(0));
+    }
+}
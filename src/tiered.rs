@@ -0,0 +1,175 @@
+//! [`TieredFetcher`][TieredFetcher] allows for falling back to less desirable proxies.
+
+use crate::{
+    errors::ApiError,
+    fetcher::Fetcher,
+    opts::Opts,
+    proxy::Proxy,
+    session::{Session, GLOBAL},
+};
+
+/// A set of [`Fetcher`][crate::fetcher::Fetcher]s ordered by preference.
+///
+/// Each tier is its own `Fetcher` with its own [`Opts`][crate::opts::Opts], and all the tiers share
+/// the same [`Session`][crate::session::Session]. Proxies are always taken from the highest tier
+/// possible, and only once a tier has run out of matching proxies will the next tier be used.
+///
+/// ```no_run
+/// use iso_country::Country;
+/// use lead_oxide::{
+///     opts::Opts,
+///     tiered::TieredFetcher,
+///     types::{Countries, Level},
+/// };
+///
+/// let mut fetcher = TieredFetcher::new(vec![
+///     // Ideally elite proxies in the US
+///     Opts::builder()
+///         .level(Level::Elite)
+///         .countries(Countries::allow().country(Country::US))
+///         .build(),
+///     // Then any anonymous proxy
+///     Opts::builder().level(Level::Anonymous).build(),
+///     // And then anything at all
+///     Opts::default(),
+/// ]);
+/// let proxies = fetcher.try_get(10).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct TieredFetcher {
+    tiers: Vec<Fetcher>,
+}
+
+impl TieredFetcher {
+    /// Creates a new `TieredFetcher` from the `tiers` ordered from most to least preferred that
+    /// uses the global [`Session`][crate::session::Session].
+    pub fn new(tiers: Vec<Opts>) -> Self {
+        Self::with_session(tiers, GLOBAL.clone())
+    }
+
+    /// Internal
+    pub(crate) fn with_session(tiers: Vec<Opts>, session: Session) -> Self {
+        Self {
+            tiers: tiers
+                .into_iter()
+                .map(|opts| session.fetcher(opts))
+                .collect(),
+        }
+    }
+
+    /// Attempts to get the specified amount of proxies pulling from each tier in order. A tier is
+    /// only considered exhausted when the API has no matching proxies for it, or when it stops
+    /// turning up any new ones, so any other error is returned immediately. If every tier is
+    /// exhausted before getting `amount` proxies then
+    /// [`ApiError::NoProxy`][crate::errors::ApiError::NoProxy] is returned. In either case any
+    /// proxies that were already gathered are kept in their tier's internal list.
+    pub fn try_get(&mut self, amount: usize) -> Result<Vec<Proxy>, ApiError> {
+        let mut gathered: Vec<(usize, Vec<Proxy>)> = Vec::new();
+        let mut count = 0;
+
+        for (i, tier) in self.tiers.iter_mut().enumerate() {
+            match tier.try_get(amount - count) {
+                Ok(proxies) => {
+                    gathered.push((i, proxies));
+                    return Ok(gathered
+                        .into_iter()
+                        .flat_map(|(_, proxies)| proxies)
+                        .collect());
+                }
+                Err(ApiError::NoProxy) | Err(ApiError::Exhausted) => {
+                    // Take whatever this tier had left before moving on
+                    let proxies = tier.take_buffered();
+                    count += proxies.len();
+                    gathered.push((i, proxies));
+                }
+                Err(err) => {
+                    self.restore(gathered);
+                    return Err(err);
+                }
+            }
+        }
+
+        self.restore(gathered);
+        Err(ApiError::NoProxy)
+    }
+
+    /// Consumes the `TieredFetcher` returning any proxies still left in each tier's internal list
+    /// ordered from the most to least preferred tier.
+    pub fn drain(self) -> Vec<Vec<Proxy>> {
+        self.tiers.into_iter().map(Fetcher::drain).collect()
+    }
+
+    fn restore(&mut self, gathered: Vec<(usize, Vec<Proxy>)>) {
+        for (i, proxies) in gathered {
+            self.tiers[i].restore(proxies);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{mock, types::Level};
+
+    fn tiers() -> Vec<Opts> {
+        vec![Opts::builder().level(Level::Elite).build(), Opts::default()]
+    }
+
+    #[test]
+    fn highest_tier_first() {
+        let mut fetcher = Session::new().tiered_fetcher(tiers());
+
+        assert_eq!(fetcher.try_get(3).unwrap().len(), 3);
+
+        // Only the first tier was used
+        let drained = fetcher.drain();
+        assert_eq!(drained[0].len(), 2);
+        assert!(drained[1].is_empty());
+    }
+
+    #[test]
+    fn falls_back() {
+        let mut fetcher = Session::new().tiered_fetcher(tiers());
+
+        // The first tier doesn't have anything
+        mock::push_response(200, "No proxy");
+        assert_eq!(fetcher.try_get(3).unwrap().len(), 3);
+
+        let drained = fetcher.drain();
+        assert!(drained[0].is_empty());
+        assert_eq!(drained[1].len(), 2);
+    }
+
+    #[test]
+    fn repeated_page() {
+        let mut fetcher = Session::new().tiered_fetcher(tiers());
+
+        // The first tier keeps returning the same couple of proxies
+        let page = mock::default_page(2);
+        mock::push_proxies(&page);
+        mock::push_proxies(&page);
+        assert_eq!(fetcher.try_get(3).unwrap().len(), 3);
+
+        // so it ran dry and the rest came from the next tier
+        let drained = fetcher.drain();
+        assert!(drained[0].is_empty());
+        assert_eq!(drained[1].len(), 4);
+    }
+
+    #[test]
+    fn all_exhausted() {
+        let mut fetcher = Session::new().tiered_fetcher(tiers());
+
+        // The first tier has a few, but the second doesn't have anything
+        mock::push_proxies(&mock::default_page(2));
+        mock::push_response(200, "No proxy");
+        mock::push_response(200, "No proxy");
+        assert!(matches!(fetcher.try_get(3), Err(ApiError::NoProxy)));
+
+        // The proxies that were found are still kept around
+        let drained = fetcher.drain();
+        assert_eq!(drained[0].len(), 2);
+        assert!(drained[1].is_empty());
+    }
+}
@@ -4,12 +4,25 @@
 //! [`TimeToConnect`][TimeToConnect] along with `enum`s for parameters with a limited number of
 //! options like [`Countries`][Countries], [`Level`][Level], and [`Protocol`][Protocol].
 
-use crate::errors::ParamError;
+use crate::errors::{ApiError, CountryError, ParamError, UnknownName};
 
-use std::{convert::TryFrom, fmt, time::Duration};
+use std::{
+    convert::TryFrom,
+    fmt,
+    io::{ErrorKind, Read},
+    str::FromStr,
+    time::Duration,
+};
 
+use chrono::{DateTime, Utc};
 use iso_country::Country;
-use serde::{Deserialize, Serialize};
+use serde::{
+    de::{
+        value::{Error as ValueError, StrDeserializer},
+        IntoDeserializer,
+    },
+    Deserialize, Serialize, Serializer,
+};
 use ureq::Response;
 
 #[derive(Clone, Copy, Debug, PartialEq, Serialize)]
@@ -79,42 +92,183 @@ bounded_val! {TimeToConnect, Duration, TIME_TO_CONNECT_BOUNDS}
 pub(crate) struct NaiveResponse {
     pub(crate) status: u16,
     pub(crate) text: String,
+    pub(crate) retry_after: Option<Duration>,
 }
 
 impl NaiveResponse {
     pub fn new(status: u16, text: String) -> Self {
-        Self { status, text }
+        Self {
+            status,
+            text,
+            retry_after: None,
+        }
+    }
+
+    /// Reads the body of `resp` erroring if it's larger than `max_bytes`
+    pub fn from_response(resp: Response, max_bytes: usize) -> Result<Self, ApiError> {
+        if let Some(ureq::Error::Io(err)) = resp.synthetic_error() {
+            if err.kind() == ErrorKind::TimedOut {
+                return Err(ApiError::Timeout);
+            }
+        }
+
+        let status = resp.status();
+        let retry_after = resp
+            .header("Retry-After")
+            .and_then(|value| parse_retry_after(value, Utc::now()));
+
+        // Read one byte past the limit so that we can tell if the body was too large
+        let mut body = Vec::new();
+        if let Err(err) = resp
+            .into_reader()
+            .take(max_bytes as u64 + 1)
+            .read_to_end(&mut body)
+        {
+            if err.kind() == ErrorKind::TimedOut {
+                return Err(ApiError::Timeout);
+            }
+            body.clear();
+        }
+
+        if body.len() > max_bytes {
+            Err(ApiError::ResponseTooLarge { limit: max_bytes })
+        } else {
+            let text = String::from_utf8_lossy(&body).into_owned();
+            Ok(Self {
+                retry_after,
+                ..Self::new(status, text)
+            })
+        }
+    }
+
+    /// The same as [`from_response`][NaiveResponse::from_response], but for `reqwest`'s async
+    /// responses. The body is read a chunk at a time so an oversized one is never fully buffered
+    #[cfg(feature = "async")]
+    pub async fn from_async_response(
+        resp: Result<reqwest::Response, reqwest::Error>,
+        max_bytes: usize,
+    ) -> Result<Self, ApiError> {
+        let mut resp = match resp {
+            Ok(resp) => resp,
+            Err(err) if err.is_timeout() => return Err(ApiError::Timeout),
+            // Match the blocking client which treats connection issues as a server error
+            Err(err) => return Ok(Self::new(500, err.to_string())),
+        };
+
+        let status = resp.status().as_u16();
+        let retry_after = resp
+            .headers()
+            .get("Retry-After")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, Utc::now()));
+
+        let mut body = Vec::new();
+        loop {
+            match resp.chunk().await {
+                Ok(Some(chunk)) => {
+                    body.extend_from_slice(&chunk);
+                    if body.len() > max_bytes {
+                        return Err(ApiError::ResponseTooLarge { limit: max_bytes });
+                    }
+                }
+                Ok(None) => break,
+                Err(err) if err.is_timeout() => return Err(ApiError::Timeout),
+                // A body that got cut off is a connection issue too
+                Err(err) => return Ok(Self::new(500, err.to_string())),
+            }
+        }
+
+        let text = String::from_utf8_lossy(&body).into_owned();
+        Ok(Self {
+            retry_after,
+            ..Self::new(status, text)
+        })
     }
 
     pub fn ok(&self) -> bool {
         (200..300).contains(&self.status)
     }
-}
 
-impl From<Response> for NaiveResponse {
-    fn from(resp: Response) -> Self {
-        let status = resp.status();
-        let text = resp.into_string().unwrap_or_default();
+    /// How long the API asked us to wait before retrying if we hit the rate limit
+    pub fn rate_limited_for(&self) -> Option<Duration> {
+        if self.status == 429 {
+            self.retry_after
+        } else {
+            None
+        }
+    }
+}
 
-        Self::new(status, text)
+// `Retry-After` can either be a number of seconds or an HTTP-date
+pub(crate) fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+
+    match value.parse::<u64>() {
+        Ok(secs) => Some(Duration::from_secs(secs)),
+        Err(_) => {
+            let retry_at = DateTime::parse_from_rfc2822(value).ok()?;
+            // A date in the past means we can retry right away
+            Some(
+                retry_at
+                    .with_timezone(&Utc)
+                    .signed_duration_since(now)
+                    .to_std()
+                    .unwrap_or_else(|_| Duration::from_secs(0)),
+            )
+        }
     }
 }
 
 #[derive(Serialize, Clone, Debug, PartialEq)]
 pub enum Countries {
-    #[serde(rename = "country")]
-    AllowList(String),
-    #[serde(rename = "not_country")]
-    BlockList(String),
+    #[serde(rename = "country", serialize_with = "serialize_codes")]
+    AllowList(Vec<Country>),
+    #[serde(rename = "not_country", serialize_with = "serialize_codes")]
+    BlockList(Vec<Country>),
+}
+
+/// Internal
+pub(crate) fn join_codes(countries: &[Country]) -> String {
+    countries
+        .iter()
+        .map(Country::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// The API takes the list as comma-separated codes
+fn serialize_codes<S: Serializer>(countries: &[Country], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&join_codes(countries))
 }
 
 impl Countries {
     pub fn allow() -> Self {
-        Self::AllowList(String::new())
+        Self::AllowList(Vec::new())
     }
 
     pub fn block() -> Self {
-        Self::BlockList(String::new())
+        Self::BlockList(Vec::new())
+    }
+
+    /// An allowlist of every country from `countries`. This takes any iterator so the countries
+    /// don't need to be collected first.
+    ///
+    /// ```
+    /// use iso_country::Country;
+    /// use lead_oxide::types::Countries;
+    ///
+    /// let nearby = [Country::US, Country::CA, Country::FR];
+    /// let countries = Countries::allow_these(nearby.iter().copied().filter(|&c| c != Country::FR));
+    /// assert_eq!(countries, Countries::allow().countries(&[Country::US, Country::CA]));
+    /// ```
+    pub fn allow_these(countries: impl IntoIterator<Item = Country>) -> Self {
+        countries.into_iter().fold(Self::allow(), Self::country)
+    }
+
+    /// A blocklist of every country from `countries`. This takes any iterator so the countries
+    /// don't need to be collected first.
+    pub fn block_these(countries: impl IntoIterator<Item = Country>) -> Self {
+        countries.into_iter().fold(Self::block(), Self::country)
     }
 
     pub fn is_empty(&self) -> bool {
@@ -124,6 +278,22 @@ impl Countries {
         }
     }
 
+    /// Internal
+    pub(crate) fn allowed(&self) -> Vec<Country> {
+        match self {
+            Self::AllowList(countries) => countries.clone(),
+            Self::BlockList(_) => Vec::new(),
+        }
+    }
+
+    /// Internal
+    pub(crate) fn permits(&self, country: Country) -> bool {
+        match self {
+            Self::AllowList(countries) => countries.contains(&country),
+            Self::BlockList(countries) => !countries.contains(&country),
+        }
+    }
+
     pub fn countries(mut self, countries: &[Country]) -> Self {
         for country in countries {
             self = self.country(*country);
@@ -132,7 +302,7 @@ impl Countries {
         self
     }
 
-    pub fn country(self, country: Country) -> Self {
+    pub fn country(mut self, country: Country) -> Self {
         // TODO: make sure this is documented. Mention that unknowns are automatically filtered out
         // if any country is used in the allow or blocklist
         if let Country::Unspecified = country {
@@ -140,18 +310,79 @@ impl Countries {
             panic!("This library doesn't allow `Unspecified` country in the allow or blocklist");
         }
 
-        let push_country = |list: String, new_tag: Country| {
-            let new_tag = new_tag.to_string();
-            if list.is_empty() {
-                new_tag
-            } else {
-                [list, new_tag].join(",")
-            }
-        };
+        match &mut self {
+            Self::AllowList(list) | Self::BlockList(list) => list.push(country),
+        }
 
-        match self {
-            Self::AllowList(list) => Self::AllowList(push_country(list, country)),
-            Self::BlockList(list) => Self::BlockList(push_country(list, country)),
+        self
+    }
+
+    /// Adds the country with the ISO 3166-1 alpha-2 `code` to the list. The code is trimmed and
+    /// uppercased first so `" us"` is the same as `Country::US`, meaning the internal list is always
+    /// stored in the same normalized form no matter how it was built.
+    ///
+    /// ```
+    /// use iso_country::Country;
+    /// use lead_oxide::types::Countries;
+    ///
+    /// let from_codes = Countries::allow().code(" us").unwrap().code("De\n").unwrap();
+    /// let from_countries = Countries::allow().countries(&[Country::US, Country::DE]);
+    /// assert_eq!(from_codes, from_countries);
+    /// ```
+    pub fn code(self, code: &str) -> Result<Self, CountryError> {
+        match code.trim().to_uppercase().parse() {
+            // An empty code parses as `Unspecified`
+            Ok(Country::Unspecified) | Err(_) => Err(CountryError::InvalidCode(code.to_string())),
+            Ok(country) => Ok(self.country(country)),
+        }
+    }
+
+    /// Adds the country with the given `name` to the list. Names are matched ignoring case and
+    /// surrounding whitespace, and if no name matches exactly then any country whose name starts
+    /// with `name` is used instead, so `"canada"` and `"Bolivia"` both work. A `name` that starts
+    /// off several countries' names is ambiguous and returns an error.
+    ///
+    /// ```
+    /// use iso_country::Country;
+    /// use lead_oxide::types::Countries;
+    ///
+    /// let from_names = Countries::allow().name("Canada").unwrap().name("germany").unwrap();
+    /// let from_countries = Countries::allow().countries(&[Country::CA, Country::DE]);
+    /// assert_eq!(from_names, from_countries);
+    ///
+    /// // There's both a North and South Korea
+    /// assert!(Countries::allow().name("Korea").is_err());
+    /// ```
+    pub fn name(self, name: &str) -> Result<Self, CountryError> {
+        let wanted = name.trim().to_lowercase();
+        if wanted.is_empty() {
+            return Err(CountryError::UnknownName(name.to_string()));
+        }
+
+        let named: Vec<_> = iso_country::data::all()
+            .into_iter()
+            .filter_map(|code| {
+                let country = code.alpha2.parse().ok()?;
+                Some((code.name.to_lowercase(), country))
+            })
+            .collect();
+
+        if let Some((_, country)) = named.iter().find(|(known, _)| *known == wanted) {
+            return Ok(self.country(*country));
+        }
+
+        let matches: Vec<Country> = named
+            .into_iter()
+            .filter(|(known, _)| known.starts_with(&wanted))
+            .map(|(_, country)| country)
+            .collect();
+        match matches.as_slice() {
+            [] => Err(CountryError::UnknownName(name.to_string())),
+            [country] => Ok(self.country(*country)),
+            _ => Err(CountryError::AmbiguousName {
+                name: name.to_string(),
+                matches,
+            }),
         }
     }
 }
@@ -163,14 +394,15 @@ impl Default for Countries {
     }
 }
 
-#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum Level {
+    Transparent,
     Anonymous,
     Elite,
 }
 
-#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "lowercase")]
 pub enum Protocol {
     Http,
@@ -178,12 +410,198 @@ pub enum Protocol {
     Socks5,
 }
 
+impl Protocol {
+    /// Internal
+    pub(crate) fn scheme(&self) -> &'static str {
+        match self {
+            Self::Http => "http",
+            Self::Socks4 => "socks4",
+            Self::Socks5 => "socks5",
+        }
+    }
+}
+
+// Parses with the same lowercase names used by serde so the two can't drift apart
+fn from_name<'a, T: Deserialize<'a>>(kind: &'static str, value: &'a str) -> Result<T, UnknownName> {
+    let deserializer: StrDeserializer<'a, ValueError> = value.into_deserializer();
+    T::deserialize(deserializer).map_err(|_| UnknownName {
+        kind,
+        value: value.to_string(),
+    })
+}
+
+/// Formats the level with the same lowercase name that the API uses, like `elite`.
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Transparent => "transparent",
+            Self::Anonymous => "anonymous",
+            Self::Elite => "elite",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// Parses the lowercase name that the API uses for the level.
+///
+/// ```
+/// use lead_oxide::types::Level;
+///
+/// assert_eq!("elite".parse(), Ok(Level::Elite));
+/// assert!("Elite".parse::<Level>().is_err());
+/// ```
+impl FromStr for Level {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        from_name("level", s)
+    }
+}
+
+/// Formats the protocol with the same lowercase name that the API uses, like `socks5`.
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.scheme())
+    }
+}
+
+/// Parses the lowercase name that the API uses for the protocol.
+///
+/// ```
+/// use lead_oxide::types::Protocol;
+///
+/// assert_eq!("socks5".parse(), Ok(Protocol::Socks5));
+/// ```
+impl FromStr for Protocol {
+    type Err = UnknownName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        from_name("protocol", s)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use std::time::Duration;
 
+    mod countries {
+        use super::*;
+
+        #[test]
+        fn normalized_codes() -> Result<(), CountryError> {
+            let countries = Countries::allow()
+                .code("us")?
+                .code("  De ")?
+                .code("\tjP\n")?
+                .country(Country::CA);
+            assert_eq!(
+                countries,
+                Countries::AllowList(vec![Country::US, Country::DE, Country::JP, Country::CA])
+            );
+
+            let countries = Countries::block().code(" ch")?;
+            assert_eq!(countries, Countries::BlockList(vec![Country::CH]));
+
+            Ok(())
+        }
+
+        #[test]
+        fn from_iterators() {
+            let codes = ["US", "DE", "ZZ", "JP"];
+            let valid = codes.iter().filter_map(|code| code.parse().ok());
+            assert_eq!(
+                Countries::allow_these(valid),
+                Countries::allow().countries(&[Country::US, Country::DE, Country::JP])
+            );
+            assert_eq!(
+                Countries::block_these(vec![Country::CH]),
+                Countries::block().country(Country::CH)
+            );
+            assert!(Countries::allow_these(Vec::new()).is_empty());
+        }
+
+        #[test]
+        fn invalid_codes() {
+            for code in &["", "  ", "USA", "u s", "zz"] {
+                assert_eq!(
+                    Countries::allow().code(code),
+                    Err(CountryError::InvalidCode(code.to_string()))
+                );
+            }
+        }
+
+        #[test]
+        fn names() -> Result<(), CountryError> {
+            // Exact names ignoring case, or an unambiguous start of one
+            let countries = Countries::block()
+                .name("Canada")?
+                .name(" JAPAN ")?
+                .name("bolivia")?
+                .name("United States of America")?;
+            assert_eq!(
+                countries,
+                Countries::block().countries(&[Country::CA, Country::JP, Country::BO, Country::US])
+            );
+
+            assert_eq!(
+                Countries::allow().name("United States"),
+                Err(CountryError::AmbiguousName {
+                    name: "United States".to_string(),
+                    matches: vec![Country::UM, Country::US],
+                })
+            );
+            for name in &["", "  ", "Atlantis"] {
+                assert_eq!(
+                    Countries::allow().name(name),
+                    Err(CountryError::UnknownName(name.to_string()))
+                );
+            }
+
+            Ok(())
+        }
+    }
+
+    mod names {
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            for level in &[Level::Transparent, Level::Anonymous, Level::Elite] {
+                assert_eq!(level.to_string().parse::<Level>(), Ok(*level));
+                assert_eq!(
+                    serde_json::to_value(level).unwrap(),
+                    serde_json::Value::String(level.to_string())
+                );
+            }
+
+            for protocol in &[Protocol::Http, Protocol::Socks4, Protocol::Socks5] {
+                assert_eq!(protocol.to_string().parse::<Protocol>(), Ok(*protocol));
+                assert_eq!(
+                    serde_json::to_value(protocol).unwrap(),
+                    serde_json::Value::String(protocol.to_string())
+                );
+            }
+        }
+
+        #[test]
+        fn unknown() {
+            assert_eq!(
+                "socks6".parse::<Protocol>(),
+                Err(UnknownName {
+                    kind: "protocol",
+                    value: "socks6".to_string()
+                })
+            );
+            assert_eq!(
+                "".parse::<Level>().unwrap_err().to_string(),
+                "'' isn't a valid level"
+            );
+        }
+    }
+
     mod bounded_vals {
         use super::*;
 
@@ -230,4 +648,80 @@ mod tests {
             assert_eq!(valid_last_checked.value(), half_hour);
         }
     }
+
+    mod naive_response {
+        use super::*;
+
+        use std::{net::TcpListener, time::Instant};
+
+        #[test]
+        fn max_response_size() {
+            let limit = 16;
+
+            // Note: `Response::new` appends a newline to the body
+            let fits = Response::new(200, "OK", &"a".repeat(limit - 1));
+            let naive_resp = NaiveResponse::from_response(fits, limit).unwrap();
+            assert_eq!(naive_resp.status, 200);
+            assert_eq!(naive_resp.text.len(), limit);
+
+            let oversized = Response::new(200, "OK", &"a".repeat(limit));
+            match NaiveResponse::from_response(oversized, limit) {
+                Err(ApiError::ResponseTooLarge { limit: err_limit }) => {
+                    assert_eq!(err_limit, limit)
+                }
+                other => panic!(
+                    "Expected `ResponseTooLarge`, got {:?}",
+                    other.map(|r| r.text)
+                ),
+            }
+        }
+
+        #[test]
+        fn timeout() {
+            // Accepts connections, but never responds
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let uri = format!("http://{}", listener.local_addr().unwrap());
+
+            let timeout = Duration::from_millis(50);
+            let start = Instant::now();
+            let resp = ureq::get(&uri).timeout(timeout).call();
+            let elapsed = start.elapsed();
+
+            assert!(matches!(
+                NaiveResponse::from_response(resp, 1024),
+                Err(ApiError::Timeout)
+            ));
+            // Timed out well before the rate limit delay would have kicked in
+            assert!(elapsed >= timeout);
+            assert!(elapsed < crate::constants::DELAY * 10, "{:?}", elapsed);
+            drop(listener);
+        }
+
+        #[cfg(feature = "async")]
+        #[tokio::test]
+        async fn max_async_response_size() {
+            let limit = 16;
+            let resp = |body: String| {
+                Ok(reqwest::Response::from(
+                    http::Response::builder()
+                        .status(429)
+                        .header("Retry-After", "2")
+                        .body(body)
+                        .unwrap(),
+                ))
+            };
+
+            let naive_resp = NaiveResponse::from_async_response(resp("a".repeat(limit)), limit)
+                .await
+                .unwrap();
+            assert_eq!(naive_resp.status, 429);
+            assert_eq!(naive_resp.text.len(), limit);
+            assert_eq!(naive_resp.rate_limited_for(), Some(Duration::from_secs(2)));
+
+            assert!(matches!(
+                NaiveResponse::from_async_response(resp("a".repeat(limit + 1)), limit).await,
+                Err(ApiError::ResponseTooLarge { limit: err_limit }) if err_limit == limit
+            ));
+        }
+    }
 }